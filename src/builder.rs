@@ -1,27 +1,219 @@
-use csv::ReaderBuilder;
+use crate::constraints::*;
+use crate::coverage::*;
+use crate::history::*;
+use crate::json::*;
+use crate::preferences::*;
+use crate::random::*;
+use crate::strategy::*;
+
+use csv::{Reader, ReaderBuilder};
+use serde_derive::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
 use std::fs::*;
 use std::io;
-use std::path::Path;
-
-enum Direction {
-    Forward,
-    Backward,
-}
+use std::path::{Path, PathBuf};
 
 pub static TEAM_NAMES: [&str; 10] = [
     "Alfa", "Bravo", "Charlie", "Delta", "Echo", "Foxtrot", "Golf", "Hotel", "India", "Juliett",
 ];
 
-#[derive(Default, Debug, Clone)]
+// Teams after the last name in TEAM_NAMES are numbered instead
+pub fn get_team_name(index: usize) -> String {
+    match TEAM_NAMES.get(index) {
+        Some(name) => name.to_string(),
+        None => (index + 1).to_string(),
+    }
+}
+
+#[derive(Debug)]
+pub enum TeamBuilderError {
+    Io(io::Error),
+    MissingHeader,
+    Malformed {
+        line: u64,
+        message: String,
+    },
+    WrongColumnCount {
+        line: u64,
+        expected: usize,
+        found: usize,
+    },
+    NotANumber {
+        line: u64,
+        column: String,
+        value: String,
+    },
+    InvalidWeight {
+        line: u64,
+        column: String,
+        value: String,
+    },
+    NoFeasibleSplit {
+        people: usize,
+        min_size: usize,
+        max_size: usize,
+    },
+    CoverageNotMet {
+        rule: String,
+        message: String,
+    },
+    GroupTooLarge {
+        constraint: String,
+        largest_team: usize,
+    },
+    TooManyPinned {
+        pinned: usize,
+        places: usize,
+    },
+    NoTeams,
+}
+
+impl fmt::Display for TeamBuilderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TeamBuilderError::Io(err) => write!(f, "cannot read file: {}", err),
+            TeamBuilderError::MissingHeader => {
+                write!(f, "missing header row with the name and skill columns")
+            }
+            TeamBuilderError::Malformed { line, message } => {
+                write!(f, "row {}: {}", line, message)
+            }
+            TeamBuilderError::WrongColumnCount {
+                line,
+                expected,
+                found,
+            } => write!(
+                f,
+                "row {}: expected {} columns, found {}",
+                line, expected, found
+            ),
+            TeamBuilderError::NotANumber {
+                line,
+                column,
+                value,
+            } => write!(
+                f,
+                "row {}, column {}: '{}' is not a number",
+                line, column, value
+            ),
+            TeamBuilderError::InvalidWeight {
+                line,
+                column,
+                value,
+            } => write!(
+                f,
+                "row {}, column {}: '{}' is not a valid weight",
+                line, column, value
+            ),
+            TeamBuilderError::NoFeasibleSplit {
+                people,
+                min_size,
+                max_size,
+            } => write!(
+                f,
+                "cannot split {} people into teams of {} to {} people",
+                people, min_size, max_size
+            ),
+            TeamBuilderError::CoverageNotMet { rule, message } => {
+                write!(f, "cannot give every team {}: {}", rule, message)
+            }
+            TeamBuilderError::GroupTooLarge {
+                constraint,
+                largest_team,
+            } => write!(
+                f,
+                "'{}' cannot be satisfied: the largest team has {} people",
+                constraint, largest_team
+            ),
+            TeamBuilderError::TooManyPinned { pinned, places } => write!(
+                f,
+                "cannot pin {} people to teams with {} places",
+                pinned, places
+            ),
+            TeamBuilderError::NoTeams => write!(f, "the teams must be generated first"),
+        }
+    }
+}
+
+impl Error for TeamBuilderError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            TeamBuilderError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for TeamBuilderError {
+    fn from(err: io::Error) -> Self {
+        TeamBuilderError::Io(err)
+    }
+}
+
+impl From<csv::Error> for TeamBuilderError {
+    fn from(err: csv::Error) -> Self {
+        let line = err.position().map_or(0, |position| position.line());
+        let message = err.to_string();
+
+        match err.into_kind() {
+            csv::ErrorKind::Io(err) => TeamBuilderError::Io(err),
+            _ => TeamBuilderError::Malformed { line, message },
+        }
+    }
+}
+
+impl From<serde_json::Error> for TeamBuilderError {
+    fn from(err: serde_json::Error) -> Self {
+        if err.is_io() {
+            return TeamBuilderError::Io(err.into());
+        }
+
+        TeamBuilderError::Malformed {
+            line: err.line() as u64,
+            message: err.to_string(),
+        }
+    }
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
 pub struct Team {
     pub people: Vec<Person>,
 }
 
-#[derive(Default, Debug, PartialEq, Clone)]
+impl Team {
+    pub fn get_average_skill_level(&self, sort_by: Option<usize>) -> f32 {
+        if self.people.is_empty() {
+            return 0.0;
+        }
+
+        let sum: f32 = self
+            .people
+            .iter()
+            .map(|person| person.get_skill_score(sort_by))
+            .sum();
+
+        sum / self.people.len() as f32
+    }
+}
+
+#[derive(Default, Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct Person {
     pub surname: String,
     pub skill_levels: Vec<u32>,
+    // Calculated from the skill levels, so rosters can leave it out
+    #[serde(default)]
     pub average_skill_level: f32,
+    // Values of the category columns, such as the gender or the class
+    #[serde(default)]
+    pub categories: Vec<String>,
+    // Values of the metadata columns, such as the email
+    #[serde(default)]
+    pub metadata: Vec<String>,
+    // Surnames of the people they would like to work with
+    #[serde(default)]
+    pub preferences: Vec<String>,
 }
 
 impl Person {
@@ -34,14 +226,235 @@ impl Person {
 
         sum as f32 / self.skill_levels.len() as f32
     }
+
+    // Without weights every skill counts the same
+    pub fn get_weighted_average_skills(&self, weights: &[f32]) -> f32 {
+        if weights.is_empty() {
+            return self.get_average_skills();
+        }
+
+        let total_weight: f32 = weights.iter().sum();
+
+        if total_weight <= 0.0 {
+            return 0.0;
+        }
+
+        let sum: f32 = self
+            .skill_levels
+            .iter()
+            .zip(weights)
+            .map(|(&level, weight)| level as f32 * weight)
+            .sum();
+
+        sum / total_weight
+    }
+
+    // The value teams are sorted and balanced on
+    pub fn get_skill_score(&self, sort_by: Option<usize>) -> f32 {
+        match sort_by {
+            None => self.average_skill_level,
+            Some(skill) => self.skill_levels[skill] as f32,
+        }
+    }
+}
+
+// What to do with the people left over when the roster
+// cannot be split evenly into teams of the chosen size
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RemainderPolicy {
+    #[default]
+    Spread,
+    ExtraTeam,
+    Reserve,
 }
 
-#[derive(Default, Clone)]
+pub static REMAINDER_POLICIES: [RemainderPolicy; 3] = [
+    RemainderPolicy::Spread,
+    RemainderPolicy::ExtraTeam,
+    RemainderPolicy::Reserve,
+];
+
+impl fmt::Display for RemainderPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let description = match self {
+            RemainderPolicy::Spread => "Leftover people join the teams, one per team",
+            RemainderPolicy::ExtraTeam => "Leftover people make an extra smaller team",
+            RemainderPolicy::Reserve => "Leftover people are kept in reserve",
+        };
+
+        write!(f, "{}", description)
+    }
+}
+
+// What a roster column holds
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColumnRole {
+    Name,
+    Skill,
+    // Such as the gender or the class, spread evenly over the teams
+    Category,
+    // Kept on each person and shown in the exports
+    Metadata,
+    // Someone the person would like to work with
+    Preference,
+    Ignored,
+}
+
+pub static COLUMN_ROLES: [ColumnRole; 6] = [
+    ColumnRole::Name,
+    ColumnRole::Skill,
+    ColumnRole::Category,
+    ColumnRole::Metadata,
+    ColumnRole::Preference,
+    ColumnRole::Ignored,
+];
+
+impl fmt::Display for ColumnRole {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            ColumnRole::Name => "Name",
+            ColumnRole::Skill => "Skill",
+            ColumnRole::Category => "Category",
+            ColumnRole::Metadata => "Metadata",
+            ColumnRole::Preference => "Preference",
+            ColumnRole::Ignored => "Ignored",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+// The role of each column, in the same order as the columns
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnMapping {
+    pub roles: Vec<ColumnRole>,
+}
+
+impl ColumnMapping {
+    // The name in the first column and skills in all the others
+    pub fn new(columns: usize) -> Self {
+        let mut roles = vec![ColumnRole::Skill; columns];
+
+        if let Some(role) = roles.first_mut() {
+            *role = ColumnRole::Name;
+        }
+
+        Self { roles }
+    }
+
+    pub fn get_columns(&self, role: ColumnRole) -> Vec<usize> {
+        self.roles
+            .iter()
+            .enumerate()
+            .filter(|(_, &other)| other == role)
+            .map(|(column, _)| column)
+            .collect()
+    }
+}
+
+// Delimiters tried when sniffing, the first one wins ties
+pub static SNIFFED_DELIMITERS: [u8; 4] = [b';', b',', b'\t', b'|'];
+
+// How many lines are looked at to guess the delimiter
+const SNIFFED_LINES: usize = 5;
+
+// How the roster CSV is written, the default
+// guesses the delimiter and expects a header row
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CsvDialect {
+    // Guessed from the first lines when None
+    pub delimiter: Option<u8>,
+    pub quote: u8,
+    // Without headers the skills are called "Skill 1", "Skill 2", ...
+    pub has_headers: bool,
+}
+
+impl Default for CsvDialect {
+    fn default() -> Self {
+        Self {
+            delimiter: None,
+            quote: b'"',
+            has_headers: true,
+        }
+    }
+}
+
+// Delimiters offered for the roster in the UI, the first one is guessed
+pub static ROSTER_DELIMITERS: [(&str, Option<u8>); 5] = [
+    ("Guess", None),
+    ("Semicolon", Some(b';')),
+    ("Comma", Some(b',')),
+    ("Tab", Some(b'\t')),
+    ("Pipe", Some(b'|')),
+];
+
+// Picks the delimiter found the same number of times on each of the
+// first lines, preferring the one found most often
+pub fn sniff_delimiter(contents: &str) -> u8 {
+    let lines: Vec<&str> = contents
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .take(SNIFFED_LINES)
+        .collect();
+
+    let mut best = (SNIFFED_DELIMITERS[0], 0);
+
+    for &delimiter in SNIFFED_DELIMITERS.iter() {
+        let counts: Vec<usize> = lines
+            .iter()
+            .map(|line| line.bytes().filter(|&byte| byte == delimiter).count())
+            .collect();
+
+        let count = match counts.first() {
+            Some(&count) if counts.iter().all(|&other| other == count) => count,
+            _ => continue,
+        };
+
+        if count > best.1 {
+            best = (delimiter, count);
+        }
+    }
+
+    best.0
+}
+
+#[derive(Clone)]
 pub struct TeamBuilder {
     pub teams: Vec<Team>,
     pub skills: Vec<String>,
+    pub skill_weights: Vec<f32>,
     pub people: Vec<Person>,
     pub people_file: String,
+    pub csv_dialect: CsvDialect,
+    // Name in the first column and skills in the others when None
+    pub column_mapping: Option<ColumnMapping>,
+    // Names of the category columns, in the same order as on each person
+    pub categories: Vec<String>,
+    // Names of the metadata columns, in the same order as on each person
+    pub metadata: Vec<String>,
+    pub constraints: Vec<Constraint>,
+    pub remainder_policy: RemainderPolicy,
+    pub reserve: Vec<Person>,
+    pub seed: Option<u64>,
+    // Replaces the names from TEAM_NAMES, in the same order
+    pub team_names: Vec<String>,
+    // Surname of each pinned person and the index of their team
+    pub pins: BTreeMap<String, usize>,
+    // Who already shared a team in the earlier rounds
+    pub past_pairings: PastPairings,
+    // How much the repeated pairings count against skill balance, 0 ignores them
+    pub repeat_weight: f32,
+    // How much the preferences count against skill balance, 0 ignores them
+    pub preference_weight: f32,
+    // Skills every team needs, see check_coverage
+    pub coverage_rules: Vec<CoverageRule>,
+}
+
+impl Default for TeamBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl TeamBuilder {
@@ -49,53 +462,338 @@ impl TeamBuilder {
         Self {
             teams: Vec::new(),
             skills: Vec::new(),
+            skill_weights: Vec::new(),
             people: Vec::new(),
             people_file: String::new(),
+            csv_dialect: CsvDialect::default(),
+            column_mapping: None,
+            categories: Vec::new(),
+            metadata: Vec::new(),
+            constraints: Vec::new(),
+            remainder_policy: RemainderPolicy::Spread,
+            reserve: Vec::new(),
+            seed: None,
+            team_names: Vec::new(),
+            pins: BTreeMap::new(),
+            past_pairings: PastPairings::default(),
+            repeat_weight: 1.0,
+            preference_weight: 1.0,
+            coverage_rules: Vec::new(),
+        }
+    }
+
+    // "Team Alfa", or the custom name if there is one
+    pub fn get_team_title(&self, index: usize) -> String {
+        match self.team_names.get(index) {
+            Some(name) if !name.trim().is_empty() => name.trim().to_string(),
+            _ => format!("Team {}", get_team_name(index)),
+        }
+    }
+
+    // Index of the team with this title or name, ignoring case,
+    // so both "Team Alfa" and "alfa" find the first team
+    pub fn find_team(&self, name: &str) -> Option<usize> {
+        let name = name.trim();
+        let teams = TEAM_NAMES
+            .len()
+            .max(self.team_names.len())
+            .max(self.teams.len());
+
+        (0..teams).find(|&index| {
+            self.get_team_title(index).eq_ignore_ascii_case(name)
+                || get_team_name(index).eq_ignore_ascii_case(name)
+        })
+    }
+
+    // Keeps the person in the given team whatever the strategy,
+    // the others are balanced around them
+    pub fn pin_person(&mut self, surname: &str, team: usize) {
+        self.pins.insert(surname.to_string(), team);
+    }
+
+    pub fn unpin_person(&mut self, surname: &str) {
+        self.pins.remove(surname);
+    }
+
+    // Pins to teams that are not generated are ignored
+    pub fn get_pinned_team(&self, surname: &str, number_of_teams: usize) -> Option<usize> {
+        self.pins
+            .get(surname)
+            .cloned()
+            .filter(|&team| team < number_of_teams)
+    }
+
+    // The pinned people of each team, in the roster order
+    pub fn get_pinned_teams(&self, number_of_teams: usize) -> Vec<Team> {
+        let mut teams = vec![Team::default(); number_of_teams];
+
+        for person in &self.people {
+            if let Some(team) = self.get_pinned_team(&person.surname, number_of_teams) {
+                teams[team].people.push(person.clone());
+            }
         }
+
+        teams
     }
 
-    pub fn load_file(&mut self, path: &Path) -> Result<(), ()> {
-        let file_contents = read_to_string(&path);
+    pub fn load_file(&mut self, path: &Path) -> Result<(), TeamBuilderError> {
+        self.people_file = read_to_string(path)?;
+        Ok(())
+    }
 
-        match file_contents {
-            Ok(contents) => {
-                self.people_file = contents;
-                Ok(())
+    pub fn load_constraints_file(&mut self, path: &Path) -> Result<(), TeamBuilderError> {
+        self.constraints = parse_constraints(&read_to_string(path)?)?;
+        Ok(())
+    }
+
+    // Replaces the preferences of the people listed in the file,
+    // see parse_preferences
+    pub fn load_preferences_file(&mut self, path: &Path) -> Result<(), TeamBuilderError> {
+        for (surname, wanted) in parse_preferences(&read_to_string(path)?)? {
+            check_preferences(&self.people, &surname, &wanted, 0)?;
+
+            match self
+                .people
+                .iter_mut()
+                .find(|person| person.surname == surname)
+            {
+                Some(person) => person.preferences = wanted,
+                None => {
+                    return Err(TeamBuilderError::Malformed {
+                        line: 0,
+                        message: format!("nobody is called '{}'", surname),
+                    });
+                }
             }
-            Err(_) => Err(()),
         }
+
+        Ok(())
     }
 
-    pub fn process_file(&mut self) -> Result<(), io::Error> {
+    // Teams of an earlier round, from a CSV or JSON export,
+    // so the same people are not put together again
+    pub fn load_previous_round_file(&mut self, path: &Path) -> Result<(), TeamBuilderError> {
+        let contents = read_to_string(path)?;
+
+        let round = if is_json_path(path) {
+            parse_round_json(&contents)?
+        } else {
+            parse_round_csv(&contents)?
+        };
+
+        self.past_pairings.add_round(&round);
+        Ok(())
+    }
+
+    // Names of the columns in the loaded file, numbered when it has no headers
+    pub fn get_columns(&self) -> Result<Vec<String>, TeamBuilderError> {
+        let mut reader = get_reader(&self.people_file, self.csv_dialect);
+        let headers = reader.headers()?;
+
+        Ok(headers
+            .iter()
+            .enumerate()
+            .map(|(index, header)| {
+                if self.csv_dialect.has_headers {
+                    header.to_string()
+                } else {
+                    format!("Column {}", index + 1)
+                }
+            })
+            .collect())
+    }
+
+    // Name in the first column, numbers are skills and anything else
+    // is kept as metadata, the weights row does not count
+    pub fn guess_column_mapping(&self) -> Result<ColumnMapping, TeamBuilderError> {
+        let mut reader = get_reader(&self.people_file, self.csv_dialect);
+        let headers = reader.headers()?.clone();
+        let mut mapping = ColumnMapping::new(headers.len());
+        let mut has_numbers = vec![false; headers.len()];
+        let mut has_text = vec![false; headers.len()];
+
+        for record in reader.records() {
+            let record = record?;
+
+            if record.get(0).is_some_and(is_weights_row) {
+                continue;
+            }
+
+            for (column, value) in record.iter().enumerate().take(headers.len()) {
+                match value.trim().parse::<u32>() {
+                    Ok(_) => has_numbers[column] = true,
+                    Err(_) => has_text[column] = true,
+                }
+            }
+        }
+
+        // A skill column with a typo stays a skill, so reading it tells
+        // which value is not a number
+        for column in 1..mapping.roles.len() {
+            if has_text[column] && !has_numbers[column] {
+                mapping.roles[column] = ColumnRole::Metadata;
+            }
+        }
+
+        // Such as "Preference 1" or "Preferred teammate"
+        if self.csv_dialect.has_headers {
+            for (column, header) in headers.iter().enumerate().skip(1) {
+                if header.to_lowercase().contains("prefer") {
+                    mapping.roles[column] = ColumnRole::Preference;
+                }
+            }
+        }
+
+        Ok(mapping)
+    }
+
+    pub fn process_file(&mut self) -> Result<(), TeamBuilderError> {
         // Reset values first
         self.teams = Vec::new();
         self.skills = Vec::new();
+        self.categories = Vec::new();
+        self.metadata = Vec::new();
 
         let mut people: Vec<Person> = Vec::new();
 
-        let mut reader = ReaderBuilder::new()
-            .delimiter(b';')
-            .from_reader(self.people_file.as_bytes());
+        let mut reader = get_reader(&self.people_file, self.csv_dialect);
+
+        // Without headers this is the first person, who is still read below
+        let headers = reader.headers()?.clone();
+
+        let mapping = match &self.column_mapping {
+            Some(mapping) => mapping.clone(),
+            None => ColumnMapping::new(headers.len()),
+        };
 
-        for skill in reader.headers()?.iter().skip(1) {
-            self.skills.push(skill.to_string());
+        if mapping.roles.len() != headers.len() {
+            return Err(TeamBuilderError::WrongColumnCount {
+                line: 1,
+                expected: mapping.roles.len(),
+                found: headers.len(),
+            });
         }
 
-        for record in reader.records() {
-            let record = record.expect("Cannot process file");
-            let mut person: Person = Person::default();
+        let name_columns = mapping.get_columns(ColumnRole::Name);
+        let skill_columns = mapping.get_columns(ColumnRole::Skill);
+        let category_columns = mapping.get_columns(ColumnRole::Category);
+        let metadata_columns = mapping.get_columns(ColumnRole::Metadata);
+        let preference_columns = mapping.get_columns(ColumnRole::Preference);
+
+        if name_columns.is_empty() || skill_columns.is_empty() {
+            return Err(TeamBuilderError::MissingHeader);
+        }
+
+        for (index, &column) in skill_columns.iter().enumerate() {
+            if self.csv_dialect.has_headers {
+                self.skills.push(headers[column].to_string());
+            } else {
+                self.skills.push(format!("Skill {}", index + 1));
+            }
+        }
+
+        let has_headers = self.csv_dialect.has_headers;
+        let get_column_name = |column: usize| {
+            if has_headers {
+                headers[column].to_string()
+            } else {
+                format!("Column {}", column + 1)
+            }
+        };
+
+        self.categories = category_columns
+            .iter()
+            .map(|&column| get_column_name(column))
+            .collect();
+        self.metadata = metadata_columns
+            .iter()
+            .map(|&column| get_column_name(column))
+            .collect();
+
+        self.skill_weights = vec![1.0; self.skills.len()];
+
+        // The line of each person, to point at unknown preferences
+        let mut lines = Vec::new();
 
-            person.surname = record[0].to_string();
+        for (index, record) in reader.records().enumerate() {
+            let record = record?;
+            let line = record.position().map_or(0, |position| position.line());
 
-            for index in 0..self.skills.len() {
-                person.skill_levels.push(
-                    record[index + 1]
-                        .parse::<u32>()
-                        .expect("Cannot push record"),
-                );
+            if record.len() != headers.len() {
+                return Err(TeamBuilderError::WrongColumnCount {
+                    line,
+                    expected: headers.len(),
+                    found: record.len(),
+                });
+            }
+
+            // An optional "Weight" row right below the header
+            if index == 0 && is_weights_row(&record[name_columns[0]]) {
+                for (index, skill) in self.skills.iter().enumerate() {
+                    let value = &record[skill_columns[index]];
+
+                    self.skill_weights[index] = match value.trim().parse::<f32>() {
+                        Ok(weight) if is_valid_weight(weight) => weight,
+                        _ => {
+                            return Err(TeamBuilderError::InvalidWeight {
+                                line,
+                                column: skill.clone(),
+                                value: value.to_string(),
+                            });
+                        }
+                    };
+                }
+
+                continue;
+            }
+
+            // First and last name columns make up a single name
+            let surname = name_columns
+                .iter()
+                .map(|&column| &record[column])
+                .filter(|name| !name.trim().is_empty())
+                .collect::<Vec<&str>>()
+                .join(" ");
+
+            let mut person = Person {
+                surname,
+                categories: category_columns
+                    .iter()
+                    .map(|&column| record[column].trim().to_string())
+                    .collect(),
+                metadata: metadata_columns
+                    .iter()
+                    .map(|&column| record[column].to_string())
+                    .collect(),
+                preferences: get_preferences(
+                    preference_columns.iter().map(|&column| &record[column]),
+                    line,
+                )?,
+                ..Person::default()
+            };
+
+            for (index, skill) in self.skills.iter().enumerate() {
+                let value = &record[skill_columns[index]];
+                let level = match value.trim().parse::<u32>() {
+                    Ok(level) => level,
+                    Err(_) => {
+                        return Err(TeamBuilderError::NotANumber {
+                            line,
+                            column: skill.clone(),
+                            value: value.to_string(),
+                        });
+                    }
+                };
+
+                person.skill_levels.push(level);
             }
 
             people.push(person);
+            lines.push(line);
+        }
+
+        for (person, &line) in people.iter().zip(&lines) {
+            check_preferences(&people, &person.surname, &person.preferences, line)?;
         }
 
         self.people = people;
@@ -103,8 +801,26 @@ impl TeamBuilder {
         Ok(())
     }
 
+    // Same as process_file, for a roster written as JSON
+    pub fn process_json(&mut self) -> Result<(), TeamBuilderError> {
+        let roster = parse_roster(&self.people_file)?;
+
+        self.teams = Vec::new();
+        self.skill_weights = if roster.weights.is_empty() {
+            vec![1.0; roster.skills.len()]
+        } else {
+            roster.weights
+        };
+        self.skills = roster.skills;
+        self.categories = roster.categories;
+        self.metadata = roster.metadata;
+        self.people = roster.people;
+
+        Ok(())
+    }
+
     pub fn check_number_of_teams(&self, people_per_team: usize) -> Option<(usize, usize)> {
-        if people_per_team >= self.people.len() {
+        if people_per_team == 0 || people_per_team >= self.people.len() {
             return None;
         }
 
@@ -114,14 +830,132 @@ impl TeamBuilder {
         Some((quotient, remainder))
     }
 
+    // Sizes of the teams when people are spread over
+    // a fixed number of teams, they differ by one at most
+    pub fn check_team_sizes(&self, number_of_teams: usize) -> Option<Vec<usize>> {
+        if number_of_teams == 0 || number_of_teams > self.people.len() {
+            return None;
+        }
+
+        let quotient = self.people.len() / number_of_teams;
+        let remainder = self.people.len() % number_of_teams;
+
+        let sizes = (0..number_of_teams)
+            .map(|index| quotient + if index < remainder { 1 } else { 0 })
+            .collect();
+
+        Some(sizes)
+    }
+
+    // Numbers of teams that keep every team between min_size and max_size
+    pub fn check_team_counts(
+        &self,
+        min_size: usize,
+        max_size: usize,
+    ) -> Result<Vec<usize>, TeamBuilderError> {
+        let people = self.people.len();
+
+        let counts: Vec<usize> = if min_size == 0 || min_size > max_size {
+            Vec::new()
+        } else {
+            (1..=people)
+                .filter(|count| count * min_size <= people && people <= count * max_size)
+                .collect()
+        };
+
+        if counts.is_empty() {
+            return Err(TeamBuilderError::NoFeasibleSplit {
+                people,
+                min_size,
+                max_size,
+            });
+        }
+
+        Ok(counts)
+    }
+
+    // Picks the number of teams whose size is the closest
+    // to the middle of the range, preferring more teams on a tie
+    pub fn choose_number_of_teams(
+        &self,
+        min_size: usize,
+        max_size: usize,
+    ) -> Result<usize, TeamBuilderError> {
+        let counts = self.check_team_counts(min_size, max_size)?;
+        let middle = (min_size + max_size) as f32 / 2.0;
+        let distance = |count: usize| (self.people.len() as f32 / count as f32 - middle).abs();
+
+        let mut best = counts[0];
+
+        for &count in &counts {
+            if distance(count) <= distance(best) {
+                best = count;
+            }
+        }
+
+        Ok(best)
+    }
+
+    // Sizes of the teams and number of people left in reserve
+    // when making teams of the given size with the remainder policy
+    pub fn get_team_layout(&self, people_per_team: usize) -> Option<(Vec<usize>, usize)> {
+        let (quotient, remainder) = self.check_number_of_teams(people_per_team)?;
+
+        let layout = match self.remainder_policy {
+            RemainderPolicy::Spread => (self.check_team_sizes(quotient)?, 0),
+            RemainderPolicy::ExtraTeam => {
+                let mut sizes = vec![people_per_team; quotient];
+
+                if remainder > 0 {
+                    sizes.push(remainder);
+                }
+
+                (sizes, 0)
+            }
+            RemainderPolicy::Reserve => (vec![people_per_team; quotient], remainder),
+        };
+
+        Some(layout)
+    }
+
+    // Weights must be as many as the skills, finite and not negative
+    pub fn set_skill_weights(&mut self, weights: Vec<f32>) -> Result<(), TeamBuilderError> {
+        if weights.len() != self.skills.len() {
+            return Err(TeamBuilderError::Malformed {
+                line: 0,
+                message: format!(
+                    "expected {} weights, found {}",
+                    self.skills.len(),
+                    weights.len()
+                ),
+            });
+        }
+
+        check_weights(&self.skills, &weights)?;
+
+        self.skill_weights = weights;
+        Ok(())
+    }
+
     pub fn calculate_teams_skill_level(&mut self) {
         for person in &mut self.people {
-            person.average_skill_level = person.get_average_skills();
+            person.average_skill_level = person.get_weighted_average_skills(&self.skill_weights);
         }
     }
 
     pub fn sort_teams_by_skill_level(&mut self, sort_by: Option<usize>) {
-        // Order from lowest to greatest
+        // With a seed, people with the same score are mixed. They are put
+        // back in a fixed order first, so the same seed gives the same teams
+        if let Some(seed) = self.seed {
+            self.people.sort_by(|a, b| {
+                a.surname
+                    .cmp(&b.surname)
+                    .then_with(|| a.skill_levels.cmp(&b.skill_levels))
+            });
+            Random::new(seed).shuffle(&mut self.people);
+        }
+
+        // Order from lowest to greatest, the sort keeps the order of ties
         match sort_by {
             None => {
                 self.people.sort_by(|a, b| {
@@ -140,47 +974,870 @@ impl TeamBuilder {
         }
     }
 
+    // Snake draft on the average skill. Panics when the people cannot be
+    // split or the rules cannot be met, where assign_people_with_strategy
+    // returns the error
     pub fn assign_people_to_team(&mut self, people_per_team: usize) {
-        let number_of_teams = self
-            .check_number_of_teams(people_per_team)
-            .expect("Cannot calculate number of people per team");
-        let mut teams: Vec<Team> = Vec::new();
-        let mut people = self.people.clone();
+        self.assign_people_with_strategy(&SnakeDraft, people_per_team, None)
+            .expect("Cannot assign people to teams");
+    }
+
+    pub fn assign_people_with_strategy(
+        &mut self,
+        strategy: &dyn AssignmentStrategy,
+        people_per_team: usize,
+        sort_by: Option<usize>,
+    ) -> Result<(), TeamBuilderError> {
+        let (team_sizes, reserved) =
+            self.get_team_layout(people_per_team)
+                .ok_or(TeamBuilderError::NoFeasibleSplit {
+                    people: self.people.len(),
+                    min_size: people_per_team,
+                    max_size: people_per_team,
+                })?;
 
-        for _ in 0..number_of_teams.0 {
-            teams.push(Team::default());
+        self.check_pinned_people(&team_sizes)?;
+        self.check_constraint_sizes(&team_sizes)?;
+        self.check_coverage(&team_sizes)?;
+
+        let reserve_indexes = self.get_reserve_indexes(reserved, team_sizes.len());
+        let mut reserve = Vec::new();
+        let mut people = Vec::new();
+
+        for (index, person) in self.people.iter().enumerate() {
+            if reserve_indexes.contains(&index) {
+                reserve.push(person.clone());
+            } else {
+                people.push(person.clone());
+            }
         }
 
-        let mut direction = Direction::Forward;
-        let mut team_index = 0;
+        self.teams = self.assign_around_pins(strategy, &people, &team_sizes, sort_by);
+        self.reserve = reserve;
+        Ok(())
+    }
+
+    // The last picks of the draft, with the lowest scores, wait in
+    // reserve. Pinned people always play and the people a coverage rule
+    // needs only wait when everybody else already does
+    fn get_reserve_indexes(&self, reserved: usize, number_of_teams: usize) -> Vec<usize> {
+        let coverage_skills = self.get_coverage_skills();
+        let mut spare = Vec::new();
+        let mut needed = Vec::new();
 
-        while !people.is_empty() {
-            let person = match people.pop() {
-                Some(person) => person,
-                None => break,
+        for (index, person) in self.people.iter().enumerate() {
+            if self
+                .get_pinned_team(&person.surname, number_of_teams)
+                .is_some()
+            {
+                continue;
+            }
+
+            let qualifies = coverage_skills
+                .iter()
+                .any(|(skill, rule)| person.skill_levels[*skill] >= rule.level);
+
+            if qualifies {
+                needed.push(index);
+            } else {
+                spare.push(index);
+            }
+        }
+
+        spare.into_iter().chain(needed).take(reserved).collect()
+    }
+
+    pub fn assign_people_in_size_range(
+        &mut self,
+        strategy: &dyn AssignmentStrategy,
+        min_size: usize,
+        max_size: usize,
+        sort_by: Option<usize>,
+    ) -> Result<(), TeamBuilderError> {
+        let number_of_teams = self.choose_number_of_teams(min_size, max_size)?;
+        self.assign_people_to_number_of_teams(strategy, number_of_teams, sort_by)
+    }
+
+    pub fn assign_people_to_number_of_teams(
+        &mut self,
+        strategy: &dyn AssignmentStrategy,
+        number_of_teams: usize,
+        sort_by: Option<usize>,
+    ) -> Result<(), TeamBuilderError> {
+        // Reports the sizes the teams would need to have
+        let people = self.people.len();
+        let teams = number_of_teams.max(1);
+        let team_sizes =
+            self.check_team_sizes(number_of_teams)
+                .ok_or(TeamBuilderError::NoFeasibleSplit {
+                    people,
+                    min_size: people / teams,
+                    max_size: people.div_ceil(teams),
+                })?;
+
+        self.check_pinned_people(&team_sizes)?;
+        self.check_constraint_sizes(&team_sizes)?;
+        self.check_coverage(&team_sizes)?;
+
+        self.teams = self.assign_around_pins(strategy, &self.people, &team_sizes, sort_by);
+        self.reserve = Vec::new();
+        Ok(())
+    }
+
+    // Puts the pinned people in their team and lets the strategy
+    // fill the places left with everybody else
+    fn assign_around_pins(
+        &self,
+        strategy: &dyn AssignmentStrategy,
+        people: &[Person],
+        team_sizes: &[usize],
+        sort_by: Option<usize>,
+    ) -> Vec<Team> {
+        let pinned = self.get_pinned_teams(team_sizes.len());
+        let free_people = self.remove_pinned_people(people, team_sizes.len());
+        let free_sizes = get_free_sizes(team_sizes, &pinned);
+
+        let mut teams = strategy.assign(self, &free_people, &free_sizes, sort_by);
+        self.repair_teams(&pinned, &mut teams, sort_by);
+
+        merge_teams(&pinned, &teams)
+    }
+
+    fn remove_pinned_people(&self, people: &[Person], number_of_teams: usize) -> Vec<Person> {
+        people
+            .iter()
+            .filter(|person| {
+                self.get_pinned_team(&person.surname, number_of_teams)
+                    .is_none()
+            })
+            .cloned()
+            .collect()
+    }
+
+    // Rounds of teams with the same sizes as the current teams, each one
+    // avoiding the pairings of the rounds before it. The people in reserve
+    // sit out every round. Every strategy is followed by the optimizer,
+    // otherwise the ones that ignore the past pairings would give the
+    // same teams each round
+    pub fn generate_schedule(
+        &self,
+        strategy: &dyn AssignmentStrategy,
+        rounds: usize,
+        sort_by: Option<usize>,
+    ) -> Result<Vec<Vec<Team>>, TeamBuilderError> {
+        if self.teams.is_empty() {
+            return Err(TeamBuilderError::NoTeams);
+        }
+
+        let team_sizes: Vec<usize> = self.teams.iter().map(|team| team.people.len()).collect();
+        self.check_pinned_people(&team_sizes)?;
+
+        let people: Vec<Person> = self
+            .people
+            .iter()
+            .filter(|person| !self.reserve.contains(person))
+            .cloned()
+            .collect();
+
+        let pinned = self.get_pinned_teams(team_sizes.len());
+        let free_people = self.remove_pinned_people(&people, team_sizes.len());
+        let free_sizes = get_free_sizes(&team_sizes, &pinned);
+
+        // The rounds of the schedule itself always count in full,
+        // repeat_weight only scales the rounds loaded before
+        let mut schedule_pairings = PastPairings::default();
+        let mut schedule = Vec::new();
+
+        for _ in 0..rounds {
+            let mut teams = strategy.assign(self, &free_people, &free_sizes, sort_by);
+            self.repair_teams(&pinned, &mut teams, sort_by);
+            local_search(&mut teams, |teams| {
+                let merged = merge_teams(&pinned, teams);
+
+                self.get_teams_cost(&merged, sort_by)
+                    + schedule_pairings.count_repeats(&merged) as f32 * REPEAT_PENALTY
+            });
+
+            let teams = merge_teams(&pinned, &teams);
+            schedule_pairings.add_round(&get_round(&teams));
+            schedule.push(teams);
+        }
+
+        Ok(schedule)
+    }
+
+    // Tells up front whether the pinned people fit in the teams, as they
+    // never wait in reserve
+    pub fn check_pinned_people(&self, team_sizes: &[usize]) -> Result<(), TeamBuilderError> {
+        let pinned = self
+            .get_pinned_teams(team_sizes.len())
+            .iter()
+            .map(|team| team.people.len())
+            .sum::<usize>();
+        let places = team_sizes.iter().sum::<usize>();
+
+        if pinned > places {
+            return Err(TeamBuilderError::TooManyPinned { pinned, places });
+        }
+
+        Ok(())
+    }
+
+    // Tells up front whether the groups kept together fit in the teams
+    pub fn check_constraint_sizes(&self, team_sizes: &[usize]) -> Result<(), TeamBuilderError> {
+        match self.get_too_large_groups(team_sizes).first() {
+            Some(ConstraintConflict::TooLarge {
+                constraint,
+                largest_team,
+            }) => Err(TeamBuilderError::GroupTooLarge {
+                constraint: constraint.to_string(),
+                largest_team: *largest_team,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    // Together constraints with more known people than the largest team
+    fn get_too_large_groups(&self, team_sizes: &[usize]) -> Vec<ConstraintConflict> {
+        let largest_team = match team_sizes.iter().max() {
+            Some(&size) => size,
+            None => return Vec::new(),
+        };
+
+        self.constraints
+            .iter()
+            .filter(|constraint| constraint.kind == ConstraintKind::Together)
+            .filter(|constraint| {
+                let known = constraint
+                    .people
+                    .iter()
+                    .filter(|surname| self.people.iter().any(|person| &person.surname == *surname))
+                    .count();
+
+                known > largest_team
+            })
+            .map(|constraint| ConstraintConflict::TooLarge {
+                constraint: constraint.clone(),
+                largest_team,
+            })
+            .collect()
+    }
+
+    // Tells up front whether every team can meet the coverage rules:
+    // the skills must exist, the teams must be big enough and enough
+    // people must qualify, counting the pinned people in their team and
+    // leaving out the ones who wait in reserve. Rules sharing people are
+    // checked one by one
+    pub fn check_coverage(&self, team_sizes: &[usize]) -> Result<(), TeamBuilderError> {
+        let pinned = self.get_pinned_teams(team_sizes.len());
+        let reserved = self
+            .people
+            .len()
+            .saturating_sub(team_sizes.iter().sum::<usize>());
+        let reserve_indexes = self.get_reserve_indexes(reserved, team_sizes.len());
+
+        for rule in &self.coverage_rules {
+            let not_met = |message: String| TeamBuilderError::CoverageNotMet {
+                rule: rule.to_string(),
+                message,
             };
 
-            teams[team_index].people.push(person);
+            let skill = self
+                .skills
+                .iter()
+                .position(|skill| skill == &rule.skill)
+                .ok_or_else(|| not_met(format!("there is no skill called '{}'", rule.skill)))?;
 
-            // FIXME: Needs refactor
-            match direction {
-                Direction::Forward => {
-                    if team_index < teams.len() - 1 {
-                        team_index += 1;
-                    } else {
-                        direction = Direction::Backward;
-                    }
+            if let Some(&size) = team_sizes.iter().min() {
+                if size < rule.count {
+                    return Err(not_met(format!("the smallest team has {} people", size)));
                 }
-                Direction::Backward => {
-                    if team_index > 0 {
-                        team_index -= 1;
-                    } else {
-                        direction = Direction::Forward;
+            }
+
+            // Qualified people still missing once the pinned ones are counted
+            let mut missing = 0;
+
+            for (index, (&size, team)) in team_sizes.iter().zip(&pinned).enumerate() {
+                let team_missing =
+                    rule.count
+                        .saturating_sub(count_qualified(&team.people, skill, rule.level));
+                let free_places = size.saturating_sub(team.people.len());
+
+                if team_missing > free_places {
+                    return Err(not_met(format!(
+                        "the people pinned to {} leave {} places",
+                        self.get_team_title(index),
+                        free_places
+                    )));
+                }
+
+                missing += team_missing;
+            }
+
+            let free_qualified = self
+                .people
+                .iter()
+                .enumerate()
+                .filter(|(index, person)| {
+                    !reserve_indexes.contains(index)
+                        && self
+                            .get_pinned_team(&person.surname, team_sizes.len())
+                            .is_none()
+                        && person.skill_levels[skill] >= rule.level
+                })
+                .count();
+
+            if free_qualified < missing {
+                let needed = rule.count * team_sizes.len();
+
+                return Err(not_met(format!(
+                    "{} people qualify, {} are needed",
+                    free_qualified + needed - missing,
+                    needed
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    // The skill index of each rule, leaving out the unknown skills
+    fn get_coverage_skills(&self) -> Vec<(usize, &CoverageRule)> {
+        self.coverage_rules
+            .iter()
+            .filter_map(|rule| {
+                self.skills
+                    .iter()
+                    .position(|skill| skill == &rule.skill)
+                    .map(|skill| (skill, rule))
+            })
+            .collect()
+    }
+
+    // Qualified people missing over all the teams and rules
+    pub fn get_coverage_shortfall(&self, teams: &[Team]) -> usize {
+        let mut shortfall = 0;
+
+        for (skill, rule) in self.get_coverage_skills() {
+            for team in teams {
+                let found = count_qualified(&team.people, skill, rule.level);
+                shortfall += rule.count.saturating_sub(found);
+            }
+        }
+
+        shortfall
+    }
+
+    // Constraint pairs broken and qualified people missing, what the
+    // repair after a strategy brings down to zero
+    fn count_broken_rules(&self, teams: &[Team]) -> usize {
+        count_violations(&self.constraints, teams) + self.get_coverage_shortfall(teams)
+    }
+
+    // Strategies other than the optimized ones ignore the constraints and
+    // the coverage rules, so people are swapped until they are all met,
+    // picking the swap that keeps the teams most balanced each time
+    fn repair_teams(&self, pinned: &[Team], teams: &mut [Team], sort_by: Option<usize>) {
+        if self.constraints.is_empty() && self.coverage_rules.is_empty() {
+            return;
+        }
+
+        let mut broken = self.count_broken_rules(&merge_teams(pinned, teams));
+
+        while broken > 0 {
+            let mut best_swap = None;
+            let mut best_cost = f32::INFINITY;
+
+            for first in 0..teams.len() {
+                for second in first + 1..teams.len() {
+                    for i in 0..teams[first].people.len() {
+                        for j in 0..teams[second].people.len() {
+                            swap_people(teams, (first, i), (second, j));
+
+                            let merged = merge_teams(pinned, teams);
+
+                            if self.count_broken_rules(&merged) < broken {
+                                let cost = self.get_teams_cost(&merged, sort_by);
+
+                                if cost < best_cost {
+                                    best_swap = Some(((first, i), (second, j)));
+                                    best_cost = cost;
+                                }
+                            }
+
+                            swap_people(teams, (first, i), (second, j));
+                        }
                     }
                 }
             }
+
+            match best_swap {
+                Some((first, second)) => swap_people(teams, first, second),
+                // The rules share people in a way no single swap can fix
+                None => break,
+            }
+
+            broken = self.count_broken_rules(&merge_teams(pinned, teams));
+        }
+    }
+
+    // Teams left without enough qualified people for a rule
+    pub fn get_coverage_shortfalls(&self) -> Vec<CoverageShortfall> {
+        let mut shortfalls = Vec::new();
+
+        for (skill, rule) in self.get_coverage_skills() {
+            for (index, team) in self.teams.iter().enumerate() {
+                let found = count_qualified(&team.people, skill, rule.level);
+
+                if found < rule.count {
+                    shortfalls.push(CoverageShortfall {
+                        team: self.get_team_title(index),
+                        rule: rule.clone(),
+                        found,
+                    });
+                }
+            }
         }
 
-        self.teams = teams;
+        shortfalls
+    }
+
+    // Difference between the strongest and the weakest team
+    pub fn get_teams_spread(&self, sort_by: Option<usize>) -> f32 {
+        get_spread(
+            self.teams
+                .iter()
+                .map(|team| team.get_average_skill_level(sort_by)),
+        )
+    }
+
+    // Swaps people between teams as long as the variance of
+    // the team averages goes down, then returns the final spread
+    pub fn optimize_teams(&mut self, sort_by: Option<usize>) -> f32 {
+        let (pinned, mut teams) = self.take_pinned_people();
+        local_search(&mut teams, |teams| {
+            self.get_teams_cost(&merge_teams(&pinned, teams), sort_by)
+        });
+        self.teams = merge_teams(&pinned, &teams);
+
+        self.get_teams_spread(sort_by)
+    }
+
+    // Same as optimize_teams, but balances every skill column together
+    // and returns the spreads summed over all the skills
+    pub fn optimize_teams_all_skills(&mut self) -> f32 {
+        let (pinned, mut teams) = self.take_pinned_people();
+        local_search(&mut teams, |teams| {
+            self.get_teams_cost_all_skills(&merge_teams(&pinned, teams))
+        });
+        self.teams = merge_teams(&pinned, &teams);
+
+        self.get_skills_spread()
     }
+
+    // Splits the current teams into the pinned people, who are not
+    // swapped, and the others
+    fn take_pinned_people(&mut self) -> (Vec<Team>, Vec<Team>) {
+        let teams = std::mem::take(&mut self.teams);
+        let pinned = self.get_pinned_teams(teams.len());
+
+        let free = teams
+            .iter()
+            .map(|team| Team {
+                people: self.remove_pinned_people(&team.people, teams.len()),
+            })
+            .collect();
+
+        (pinned, free)
+    }
+
+    // What the optimizer minimizes: the variance of the team averages,
+    // how unevenly the categories are spread, small penalties for every
+    // repeated pairing and preference left out and a large one for every
+    // broken constraint or person missing from a coverage rule
+    pub fn get_teams_cost(&self, teams: &[Team], sort_by: Option<usize>) -> f32 {
+        get_teams_variance(teams, sort_by)
+            + get_category_imbalance(teams)
+            + self.past_pairings.count_repeats(teams) as f32 * REPEAT_PENALTY * self.repeat_weight
+            + count_unmet_preferences(teams) as f32 * PREFERENCE_PENALTY * self.preference_weight
+            + self.count_broken_rules(teams) as f32 * CONSTRAINT_PENALTY
+    }
+
+    pub fn get_teams_cost_all_skills(&self, teams: &[Team]) -> f32 {
+        (0..self.skills.len())
+            .map(|skill| get_teams_variance(teams, Some(skill)))
+            .sum::<f32>()
+            + get_category_imbalance(teams)
+            + self.past_pairings.count_repeats(teams) as f32 * REPEAT_PENALTY * self.repeat_weight
+            + count_unmet_preferences(teams) as f32 * PREFERENCE_PENALTY * self.preference_weight
+            + self.count_broken_rules(teams) as f32 * CONSTRAINT_PENALTY
+    }
+
+    pub fn get_skills_spread(&self) -> f32 {
+        self.get_skill_balance_report()
+            .iter()
+            .map(|balance| balance.spread)
+            .sum()
+    }
+
+    // Constraints that name unknown people, contradict each other, do not
+    // fit in the current teams or are still broken by them
+    pub fn get_constraint_conflicts(&self) -> Vec<ConstraintConflict> {
+        let mut conflicts = Vec::new();
+
+        for constraint in &self.constraints {
+            for surname in &constraint.people {
+                if !self.people.iter().any(|person| &person.surname == surname) {
+                    conflicts.push(ConstraintConflict::UnknownPerson {
+                        constraint: constraint.clone(),
+                        surname: surname.clone(),
+                    });
+                }
+            }
+        }
+
+        let together = self
+            .constraints
+            .iter()
+            .filter(|constraint| constraint.kind == ConstraintKind::Together);
+
+        for together in together {
+            let apart = self
+                .constraints
+                .iter()
+                .filter(|constraint| constraint.kind == ConstraintKind::Apart);
+
+            for apart in apart {
+                let shared = apart
+                    .people
+                    .iter()
+                    .filter(|surname| together.people.contains(surname))
+                    .count();
+
+                if shared >= 2 {
+                    conflicts.push(ConstraintConflict::Contradiction {
+                        together: together.clone(),
+                        apart: apart.clone(),
+                    });
+                }
+            }
+        }
+
+        let team_sizes: Vec<usize> = self.teams.iter().map(|team| team.people.len()).collect();
+        conflicts.extend(self.get_too_large_groups(&team_sizes));
+
+        let team_of = get_team_of(&self.teams);
+
+        for constraint in &self.constraints {
+            let already_reported = conflicts.iter().any(|conflict| match conflict {
+                ConstraintConflict::UnknownPerson { constraint: c, .. } => c == constraint,
+                ConstraintConflict::Contradiction { together, apart } => {
+                    together == constraint || apart == constraint
+                }
+                ConstraintConflict::TooLarge { constraint: c, .. } => c == constraint,
+                ConstraintConflict::Unsatisfied(c) => c == constraint,
+            });
+
+            if !already_reported && constraint.count_violations(&team_of) > 0 {
+                conflicts.push(ConstraintConflict::Unsatisfied(constraint.clone()));
+            }
+        }
+
+        conflicts
+    }
+
+    pub fn get_skill_balance_report(&self) -> Vec<SkillBalance> {
+        self.skills
+            .iter()
+            .enumerate()
+            .map(|(index, skill)| {
+                let team_averages: Vec<f32> = self
+                    .teams
+                    .iter()
+                    .map(|team| team.get_average_skill_level(Some(index)))
+                    .collect();
+
+                SkillBalance {
+                    skill: skill.clone(),
+                    spread: get_spread(team_averages.iter().cloned()),
+                    team_averages,
+                }
+            })
+            .collect()
+    }
+
+    // Pairs in the current teams that were together in earlier rounds
+    pub fn get_repeated_pairings(&self) -> Vec<RepeatedPairing> {
+        self.past_pairings.get_repeated_pairings(&self.teams)
+    }
+
+    // For everybody who asked for teammates, who they got. People in
+    // reserve get nobody
+    pub fn get_preference_report(&self) -> Vec<PreferenceSatisfaction> {
+        let team_of = get_team_of(&self.teams);
+
+        self.people
+            .iter()
+            .filter(|person| !person.preferences.is_empty())
+            .map(|person| {
+                let team = team_of.get(person.surname.as_str());
+
+                PreferenceSatisfaction {
+                    surname: person.surname.clone(),
+                    wanted: person.preferences.clone(),
+                    granted: person
+                        .preferences
+                        .iter()
+                        .filter(|surname| team.is_some() && team_of.get(surname.as_str()) == team)
+                        .cloned()
+                        .collect(),
+                }
+            })
+            .collect()
+    }
+
+    // Share of all the preferences that were granted, 1 when there are none
+    pub fn get_preference_satisfaction(&self) -> f32 {
+        let report = self.get_preference_report();
+        let wanted: usize = report.iter().map(|person| person.wanted.len()).sum();
+        let granted: usize = report.iter().map(|person| person.granted.len()).sum();
+
+        if wanted == 0 {
+            1.0
+        } else {
+            granted as f32 / wanted as f32
+        }
+    }
+
+    // How many people of each category value are in each team
+    pub fn get_category_balance_report(&self) -> Vec<CategoryBalance> {
+        let mut report = Vec::new();
+
+        for (index, category) in self.categories.iter().enumerate() {
+            for (value, team_counts) in get_category_counts(&self.teams, index) {
+                report.push(CategoryBalance {
+                    category: category.clone(),
+                    value: value.to_string(),
+                    team_counts,
+                });
+            }
+        }
+
+        report
+    }
+}
+
+// Places left in each team once the pinned people are in. A team with
+// more pinned people than places takes them from the roomiest team
+fn get_free_sizes(team_sizes: &[usize], pinned: &[Team]) -> Vec<usize> {
+    let mut sizes = team_sizes.to_vec();
+
+    for (index, team) in pinned.iter().enumerate() {
+        while sizes[index] < team.people.len() {
+            let roomiest = (0..sizes.len())
+                .filter(|&other| sizes[other] > pinned[other].people.len())
+                .max_by_key(|&other| sizes[other] - pinned[other].people.len())
+                .expect("Cannot find a team with a free place");
+
+            sizes[roomiest] -= 1;
+            sizes[index] += 1;
+        }
+    }
+
+    sizes
+        .iter()
+        .zip(pinned)
+        .map(|(size, team)| size - team.people.len())
+        .collect()
+}
+
+// The pinned people of each team followed by the ones given by the strategy
+pub fn merge_teams(pinned: &[Team], teams: &[Team]) -> Vec<Team> {
+    pinned
+        .iter()
+        .zip(teams)
+        .map(|(pinned, team)| Team {
+            people: pinned.people.iter().chain(&team.people).cloned().collect(),
+        })
+        .collect()
+}
+
+// Number of people in each team for every value of a category,
+// sorted by value
+pub fn get_category_counts(teams: &[Team], category: usize) -> BTreeMap<&str, Vec<usize>> {
+    let mut counts = BTreeMap::new();
+
+    for (index, team) in teams.iter().enumerate() {
+        for person in &team.people {
+            if let Some(value) = person.categories.get(category) {
+                counts
+                    .entry(value.as_str())
+                    .or_insert_with(|| vec![0; teams.len()])[index] += 1;
+            }
+        }
+    }
+
+    counts
+}
+
+// Reads the roster with the CSV dialect, the BOM
+// that spreadsheet programs often add is skipped
+fn get_reader(people_file: &str, dialect: CsvDialect) -> Reader<&[u8]> {
+    let contents = people_file.trim_start_matches('\u{feff}');
+
+    // Row lengths are checked by hand to report them as WrongColumnCount
+    ReaderBuilder::new()
+        .delimiter(
+            dialect
+                .delimiter
+                .unwrap_or_else(|| sniff_delimiter(contents)),
+        )
+        .quote(dialect.quote)
+        .has_headers(dialect.has_headers)
+        .flexible(true)
+        .from_reader(contents.as_bytes())
+}
+
+fn is_weights_row(name: &str) -> bool {
+    let name = name.trim().to_lowercase();
+    name == "weight" || name == "weights"
+}
+
+// Weights scale the skills, so they must be finite and not negative
+pub fn is_valid_weight(weight: f32) -> bool {
+    weight.is_finite() && weight >= 0.0
+}
+
+// Each weight goes with the skill at the same index
+pub fn check_weights(skills: &[String], weights: &[f32]) -> Result<(), TeamBuilderError> {
+    for (skill, weight) in skills.iter().zip(weights) {
+        if !is_valid_weight(*weight) {
+            return Err(TeamBuilderError::InvalidWeight {
+                line: 0,
+                column: skill.clone(),
+                value: weight.to_string(),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+// The files that go with "roster.csv" are named after it,
+// such as "roster_constraints.csv" for the "constraints" suffix
+pub fn get_roster_sibling_path(roster_path: &Path, suffix: &str) -> PathBuf {
+    let stem = roster_path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    roster_path.with_file_name(format!("{}_{}.csv", stem, suffix))
+}
+
+// How a single skill is spread across the teams
+#[derive(Debug, Clone, PartialEq)]
+pub struct SkillBalance {
+    pub skill: String,
+    pub team_averages: Vec<f32>,
+    pub spread: f32,
+}
+
+impl fmt::Display for SkillBalance {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let averages: Vec<String> = self
+            .team_averages
+            .iter()
+            .map(|average| format!("{:.2}", average))
+            .collect();
+
+        write!(
+            f,
+            "{}: {} (spread {:.2})",
+            self.skill,
+            averages.join(", "),
+            self.spread
+        )
+    }
+}
+
+// Ideally each team gets a share of every category value in proportion
+// to its size. The difference is counted in people, so a single person
+// out of place outweighs the usual skill variance
+fn get_category_imbalance(teams: &[Team]) -> f32 {
+    let people: usize = teams.iter().map(|team| team.people.len()).sum();
+
+    let categories = teams
+        .iter()
+        .flat_map(|team| &team.people)
+        .map(|person| person.categories.len())
+        .max()
+        .unwrap_or(0);
+
+    let mut imbalance = 0.0;
+
+    for category in 0..categories {
+        for team_counts in get_category_counts(teams, category).values() {
+            let total: usize = team_counts.iter().sum();
+
+            for (team, &count) in teams.iter().zip(team_counts) {
+                let expected = (total * team.people.len()) as f32 / people as f32;
+                imbalance += (count as f32 - expected).powi(2);
+            }
+        }
+    }
+
+    imbalance
+}
+
+// How a single category value is spread across the teams
+#[derive(Debug, Clone, PartialEq)]
+pub struct CategoryBalance {
+    pub category: String,
+    pub value: String,
+    pub team_counts: Vec<usize>,
+}
+
+impl fmt::Display for CategoryBalance {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let counts: Vec<String> = self
+            .team_counts
+            .iter()
+            .map(|count| count.to_string())
+            .collect();
+
+        write!(f, "{} {}: {}", self.category, self.value, counts.join(", "))
+    }
+}
+
+fn get_spread<I>(averages: I) -> f32
+where
+    I: Iterator<Item = f32> + Clone,
+{
+    let max = averages.clone().fold(f32::MIN, f32::max);
+    let min = averages.fold(f32::MAX, f32::min);
+
+    if max < min {
+        return 0.0;
+    }
+
+    max - min
+}
+
+fn get_teams_variance(teams: &[Team], sort_by: Option<usize>) -> f32 {
+    if teams.is_empty() {
+        return 0.0;
+    }
+
+    let averages: Vec<f32> = teams
+        .iter()
+        .map(|team| team.get_average_skill_level(sort_by))
+        .collect();
+
+    let mean = averages.iter().sum::<f32>() / averages.len() as f32;
+
+    averages
+        .iter()
+        .map(|average| (average - mean).powi(2))
+        .sum::<f32>()
+        / averages.len() as f32
 }
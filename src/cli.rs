@@ -0,0 +1,695 @@
+use crate::builder::*;
+use crate::constraints::*;
+use crate::coverage::*;
+use crate::csv_exporter::*;
+use crate::html_exporter::*;
+use crate::json::*;
+use crate::preferences::*;
+use crate::random::*;
+use crate::strategy::*;
+use crate::text_exporter::*;
+
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+
+pub const EXIT_OK: i32 = 0;
+pub const EXIT_USAGE: i32 = 2;
+pub const EXIT_IO: i32 = 3;
+pub const EXIT_MISSING_HEADER: i32 = 4;
+pub const EXIT_MALFORMED: i32 = 5;
+pub const EXIT_WRONG_COLUMN_COUNT: i32 = 6;
+pub const EXIT_NOT_A_NUMBER: i32 = 7;
+pub const EXIT_INVALID_WEIGHT: i32 = 8;
+pub const EXIT_NO_FEASIBLE_SPLIT: i32 = 9;
+pub const EXIT_CONSTRAINT_CONFLICTS: i32 = 10;
+pub const EXIT_COVERAGE_NOT_MET: i32 = 11;
+
+const USAGE: &str = "Usage:
+  team-builder generate --input <roster> <team layout> [options]
+  team-builder check --input <roster>
+  team-builder help
+
+The roster is a CSV file, or JSON if its name ends in .json.
+
+Team layout, pick one:
+  --team-size <n>              Teams of n people
+  --teams <n>                  Exactly n teams
+  --min-size <n> --max-size <m>
+                               Teams of n to m people
+
+Options:
+  --sort-by <skill>            Skill to balance on, or \"average\" (default)
+  --strategy <name>            optimized (default), optimized-all-skills,
+                               snake-draft, round-robin, random-shuffle
+  --remainder <policy>         spread (default), extra-team, reserve
+  --seed <number>              Seed to mix people with the same score
+  --constraints <file.csv>     Defaults to <roster>_constraints.csv if present
+  --preferences <file.csv>     Defaults to <roster>_preferences.csv if present
+  --preference-weight <w>      How much preferences count, 1 by default, 0 ignores them
+  --team-names <a,b,...>       Names used instead of Alfa, Bravo, ...
+  --pin <name>=<team>          Keeps a person in a team, can be repeated
+  --require <rule,...>         Skills every team needs, such as Programming>=3,
+                               or 2:Graphics>=2 for two people per team
+  --previous <file>            Teams of an earlier round, exported as CSV or
+                               JSON, to avoid repeating pairings. Can be repeated
+  --repeat-weight <w>          How much repeated pairings count, 1 by default,
+                               0 ignores them
+  --format <format>            html (default), json, csv, markdown, text
+  --delimiter <delimiter>      Between CSV columns, ; (default) or ,
+  --input-delimiter <delimiter>
+                               Between roster columns: ; , tab or |,
+                               guessed from the first lines by default
+  --quote <character>          Around roster fields, \" by default
+  --headers <yes|no>           Whether the roster starts with a header row
+  --name-columns <a,b,...>     Columns joined into the name, by default the first
+  --category-columns <a,b,...> Columns such as the gender,
+                               spread evenly over the teams
+  --metadata-columns <a,b,...> Columns kept with each person and exported
+  --preference-columns <a,b,...>
+                               Columns with the people each person would like
+                               to work with, up to three
+  --ignore-columns <a,b,...>   Columns left out, all the others are skills
+  --output <file>              Defaults to the standard output
+
+Without column options the first column is the name, the columns
+without numbers are metadata and the others are skills.
+
+Without arguments the graphical interface is opened,
+if it was built with the gui feature.";
+
+#[derive(Debug)]
+pub enum CliError {
+    Usage(String),
+    Builder(TeamBuilderError),
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CliError::Usage(message) => write!(f, "{}", message),
+            CliError::Builder(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl From<TeamBuilderError> for CliError {
+    fn from(err: TeamBuilderError) -> Self {
+        CliError::Builder(err)
+    }
+}
+
+impl From<io::Error> for CliError {
+    fn from(err: io::Error) -> Self {
+        CliError::Builder(TeamBuilderError::Io(err))
+    }
+}
+
+impl CliError {
+    pub fn get_exit_code(&self) -> i32 {
+        match self {
+            CliError::Usage(_) => EXIT_USAGE,
+            CliError::Builder(err) => match err {
+                TeamBuilderError::Io(_) => EXIT_IO,
+                TeamBuilderError::MissingHeader => EXIT_MISSING_HEADER,
+                TeamBuilderError::Malformed { .. } => EXIT_MALFORMED,
+                TeamBuilderError::WrongColumnCount { .. } => EXIT_WRONG_COLUMN_COUNT,
+                TeamBuilderError::NotANumber { .. } => EXIT_NOT_A_NUMBER,
+                TeamBuilderError::InvalidWeight { .. } => EXIT_INVALID_WEIGHT,
+                TeamBuilderError::NoFeasibleSplit { .. } => EXIT_NO_FEASIBLE_SPLIT,
+                TeamBuilderError::CoverageNotMet { .. } => EXIT_COVERAGE_NOT_MET,
+                TeamBuilderError::GroupTooLarge { .. } => EXIT_CONSTRAINT_CONFLICTS,
+                TeamBuilderError::TooManyPinned { .. } => EXIT_NO_FEASIBLE_SPLIT,
+                TeamBuilderError::NoTeams => EXIT_USAGE,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TeamLayout {
+    TeamSize(usize),
+    Teams(usize),
+    SizeRange(usize, usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Html,
+    Json,
+    // With the delimiter between the columns
+    Csv(u8),
+    Markdown,
+    Text,
+}
+
+impl Format {
+    fn parse(value: &str) -> Result<Self, CliError> {
+        match value {
+            "html" => Ok(Format::Html),
+            "json" => Ok(Format::Json),
+            "csv" => Ok(Format::Csv(b';')),
+            "markdown" => Ok(Format::Markdown),
+            "text" => Ok(Format::Text),
+            _ => Err(CliError::Usage(format!("unknown format '{}'", value))),
+        }
+    }
+}
+
+// Roster columns picked by name, the ones not listed are skills
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ColumnNames {
+    pub name: Vec<String>,
+    pub category: Vec<String>,
+    pub metadata: Vec<String>,
+    pub preference: Vec<String>,
+    pub ignored: Vec<String>,
+}
+
+impl ColumnNames {
+    // Without any column names the mapping is guessed, as in the
+    // graphical interface
+    fn get_mapping(&self, tb: &TeamBuilder) -> Result<ColumnMapping, CliError> {
+        if *self == ColumnNames::default() {
+            return Ok(tb.guess_column_mapping()?);
+        }
+
+        let columns = tb.get_columns()?;
+        let mut mapping = ColumnMapping::new(columns.len());
+
+        if !self.name.is_empty() {
+            mapping.roles = vec![ColumnRole::Skill; columns.len()];
+        }
+
+        let roles = [
+            (&self.name, ColumnRole::Name),
+            (&self.category, ColumnRole::Category),
+            (&self.metadata, ColumnRole::Metadata),
+            (&self.preference, ColumnRole::Preference),
+            (&self.ignored, ColumnRole::Ignored),
+        ];
+
+        for (names, role) in roles.iter() {
+            for name in names.iter() {
+                let column = columns
+                    .iter()
+                    .position(|column| column == name)
+                    .ok_or_else(|| {
+                        CliError::Usage(format!(
+                            "unknown column '{}', expected one of: {}",
+                            name,
+                            columns.join(", ")
+                        ))
+                    })?;
+
+                mapping.roles[column] = *role;
+            }
+        }
+
+        Ok(mapping)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GenerateOptions {
+    pub input: PathBuf,
+    pub layout: TeamLayout,
+    pub sort_by: Option<String>,
+    pub strategy: String,
+    pub remainder_policy: RemainderPolicy,
+    pub seed: Option<u64>,
+    pub csv_dialect: CsvDialect,
+    pub columns: ColumnNames,
+    pub constraints: Option<PathBuf>,
+    pub preferences: Option<PathBuf>,
+    pub preference_weight: f32,
+    pub team_names: Vec<String>,
+    // Name of each pinned person and of their team
+    pub pins: Vec<(String, String)>,
+    pub coverage_rules: Vec<CoverageRule>,
+    // Exports of the earlier rounds
+    pub previous_rounds: Vec<PathBuf>,
+    pub repeat_weight: f32,
+    pub format: Format,
+    pub output: Option<PathBuf>,
+}
+
+// Command line name of a strategy, "Optimized (all skills)" is "optimized-all-skills"
+pub fn get_strategy_key(strategy: &dyn AssignmentStrategy) -> String {
+    strategy
+        .name()
+        .to_lowercase()
+        .replace(&['(', ')'][..], "")
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join("-")
+}
+
+fn parse_remainder_policy(value: &str) -> Result<RemainderPolicy, CliError> {
+    match value {
+        "spread" => Ok(RemainderPolicy::Spread),
+        "extra-team" => Ok(RemainderPolicy::ExtraTeam),
+        "reserve" => Ok(RemainderPolicy::Reserve),
+        _ => Err(CliError::Usage(format!(
+            "unknown remainder policy '{}'",
+            value
+        ))),
+    }
+}
+
+fn parse_delimiter(value: &str) -> Result<u8, CliError> {
+    match value {
+        ";" | "semicolon" => Ok(b';'),
+        "," | "comma" => Ok(b','),
+        "\t" | "\\t" | "tab" => Ok(b'\t'),
+        "|" | "pipe" => Ok(b'|'),
+        _ => Err(CliError::Usage(format!("unknown delimiter '{}'", value))),
+    }
+}
+
+fn parse_quote(value: &str) -> Result<u8, CliError> {
+    match value.as_bytes() {
+        [quote] => Ok(*quote),
+        _ => Err(CliError::Usage(format!(
+            "--quote expects a single character, found '{}'",
+            value
+        ))),
+    }
+}
+
+fn parse_yes_no(option: &str, value: &str) -> Result<bool, CliError> {
+    match value {
+        "yes" => Ok(true),
+        "no" => Ok(false),
+        _ => Err(CliError::Usage(format!(
+            "{} expects yes or no, found '{}'",
+            option, value
+        ))),
+    }
+}
+
+// "a, b,c" is ["a", "b", "c"]
+fn parse_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|item| item.trim().to_string())
+        .collect()
+}
+
+fn parse_pin(value: &str) -> Result<(String, String), CliError> {
+    match value.rfind('=') {
+        Some(position) if position > 0 && position + 1 < value.len() => Ok((
+            value[..position].trim().to_string(),
+            value[position + 1..].trim().to_string(),
+        )),
+        _ => Err(CliError::Usage(format!(
+            "--pin expects <name>=<team>, found '{}'",
+            value
+        ))),
+    }
+}
+
+fn parse_weight(option: &str, value: &str) -> Result<f32, CliError> {
+    match parse_number::<f32>(option, value)? {
+        weight if is_valid_weight(weight) => Ok(weight),
+        _ => Err(CliError::Usage(format!(
+            "{} expects a positive number, found '{}'",
+            option, value
+        ))),
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(option: &str, value: &str) -> Result<T, CliError> {
+    value
+        .parse::<T>()
+        .map_err(|_| CliError::Usage(format!("{} expects a number, found '{}'", option, value)))
+}
+
+pub fn parse_generate_args(args: &[String]) -> Result<GenerateOptions, CliError> {
+    let mut input = None;
+    let mut team_size = None;
+    let mut teams = None;
+    let mut min_size = None;
+    let mut max_size = None;
+    let mut sort_by = None;
+    let mut strategy = "optimized".to_string();
+    let mut remainder_policy = RemainderPolicy::Spread;
+    let mut seed = None;
+    let mut csv_dialect = CsvDialect::default();
+    let mut columns = ColumnNames::default();
+    let mut constraints = None;
+    let mut preferences = None;
+    let mut preference_weight = 1.0;
+    let mut team_names = Vec::new();
+    let mut pins = Vec::new();
+    let mut coverage_rules = Vec::new();
+    let mut previous_rounds = Vec::new();
+    let mut repeat_weight = 1.0;
+    let mut format = Format::Html;
+    let mut delimiter = None;
+    let mut output = None;
+
+    let mut args = args.iter();
+
+    while let Some(option) = args.next() {
+        let value = match args.next() {
+            Some(value) => value,
+            None => {
+                return Err(CliError::Usage(format!("{} expects a value", option)));
+            }
+        };
+
+        match option.as_str() {
+            "--input" => input = Some(PathBuf::from(value)),
+            "--team-size" => team_size = Some(parse_number(option, value)?),
+            "--teams" => teams = Some(parse_number(option, value)?),
+            "--min-size" => min_size = Some(parse_number(option, value)?),
+            "--max-size" => max_size = Some(parse_number(option, value)?),
+            "--sort-by" => sort_by = Some(value.clone()),
+            "--strategy" => strategy = value.clone(),
+            "--remainder" => remainder_policy = parse_remainder_policy(value)?,
+            "--seed" => seed = Some(parse_number(option, value)?),
+            "--input-delimiter" => csv_dialect.delimiter = Some(parse_delimiter(value)?),
+            "--quote" => csv_dialect.quote = parse_quote(value)?,
+            "--headers" => csv_dialect.has_headers = parse_yes_no(option, value)?,
+            "--name-columns" => columns.name = parse_list(value),
+            "--category-columns" => columns.category = parse_list(value),
+            "--metadata-columns" => columns.metadata = parse_list(value),
+            "--preference-columns" => columns.preference = parse_list(value),
+            "--ignore-columns" => columns.ignored = parse_list(value),
+            "--constraints" => constraints = Some(PathBuf::from(value)),
+            "--preferences" => preferences = Some(PathBuf::from(value)),
+            "--preference-weight" => preference_weight = parse_weight(option, value)?,
+            "--team-names" => team_names = parse_list(value),
+            "--pin" => pins.push(parse_pin(value)?),
+            "--require" => coverage_rules.extend(
+                parse_coverage_rules(value).map_err(|err| CliError::Usage(err.to_string()))?,
+            ),
+            "--previous" => previous_rounds.push(PathBuf::from(value)),
+            "--repeat-weight" => repeat_weight = parse_weight(option, value)?,
+            "--format" => format = Format::parse(value)?,
+            "--delimiter" => delimiter = Some(parse_delimiter(value)?),
+            "--output" => output = Some(PathBuf::from(value)),
+            _ => return Err(CliError::Usage(format!("unknown option '{}'", option))),
+        }
+    }
+
+    let input = input.ok_or_else(|| CliError::Usage("--input is required".to_string()))?;
+
+    let format = match (format, delimiter) {
+        (Format::Csv(_), Some(delimiter)) => Format::Csv(delimiter),
+        (_, Some(_)) => {
+            return Err(CliError::Usage(
+                "--delimiter only works with --format csv".to_string(),
+            ));
+        }
+        (format, None) => format,
+    };
+
+    let layout = match (team_size, teams, min_size, max_size) {
+        (Some(size), None, None, None) => TeamLayout::TeamSize(size),
+        (None, Some(teams), None, None) => TeamLayout::Teams(teams),
+        (None, None, Some(min), Some(max)) => TeamLayout::SizeRange(min, max),
+        _ => {
+            return Err(CliError::Usage(
+                "use either --team-size, --teams or --min-size with --max-size".to_string(),
+            ));
+        }
+    };
+
+    Ok(GenerateOptions {
+        input,
+        layout,
+        sort_by,
+        strategy,
+        remainder_policy,
+        seed,
+        csv_dialect,
+        columns,
+        constraints,
+        preferences,
+        preference_weight,
+        team_names,
+        pins,
+        coverage_rules,
+        previous_rounds,
+        repeat_weight,
+        format,
+        output,
+    })
+}
+
+// Loads the roster with its constraints and preferences
+fn load_roster(
+    input: &Path,
+    csv_dialect: CsvDialect,
+    columns: &ColumnNames,
+    constraints: Option<&Path>,
+    preferences: Option<&Path>,
+) -> Result<TeamBuilder, CliError> {
+    let mut tb = TeamBuilder::new();
+    tb.csv_dialect = csv_dialect;
+    tb.load_file(input)?;
+
+    if is_json_path(input) {
+        tb.process_json()?;
+    } else {
+        tb.column_mapping = Some(columns.get_mapping(&tb)?);
+        tb.process_file()?;
+    }
+
+    match constraints {
+        Some(path) => tb.load_constraints_file(path)?,
+        None => {
+            let path = get_constraints_path(input);
+
+            if path.exists() {
+                tb.load_constraints_file(&path)?;
+            }
+        }
+    }
+
+    match preferences {
+        Some(path) => tb.load_preferences_file(path)?,
+        None => {
+            let path = get_preferences_path(input);
+
+            if path.exists() {
+                tb.load_preferences_file(&path)?;
+            }
+        }
+    }
+
+    Ok(tb)
+}
+
+fn get_sort_by(tb: &TeamBuilder, skill: Option<&String>) -> Result<Option<usize>, CliError> {
+    let skill = match skill {
+        Some(skill) if !skill.eq_ignore_ascii_case("average") => skill,
+        _ => return Ok(None),
+    };
+
+    match tb.skills.iter().position(|name| name == skill) {
+        Some(index) => Ok(Some(index)),
+        None => Err(CliError::Usage(format!(
+            "unknown skill '{}', expected one of: {}",
+            skill,
+            tb.skills.join(", ")
+        ))),
+    }
+}
+
+// Builds the teams, returning the TeamBuilder with the teams in it
+pub fn generate(options: &GenerateOptions) -> Result<TeamBuilder, CliError> {
+    let mut tb = load_roster(
+        &options.input,
+        options.csv_dialect,
+        &options.columns,
+        options.constraints.as_deref(),
+        options.preferences.as_deref(),
+    )?;
+
+    let seed = options.seed.unwrap_or_else(seed_from_time);
+    tb.seed = Some(seed);
+    tb.remainder_policy = options.remainder_policy;
+    tb.team_names = options.team_names.clone();
+    tb.preference_weight = options.preference_weight;
+    tb.repeat_weight = options.repeat_weight;
+    tb.coverage_rules = options.coverage_rules.clone();
+
+    for path in &options.previous_rounds {
+        tb.load_previous_round_file(path)?;
+    }
+
+    for (surname, team) in &options.pins {
+        if !tb.people.iter().any(|person| &person.surname == surname) {
+            return Err(CliError::Usage(format!(
+                "cannot pin unknown person '{}'",
+                surname
+            )));
+        }
+
+        let index = tb
+            .find_team(team)
+            .ok_or_else(|| CliError::Usage(format!("cannot pin to unknown team '{}'", team)))?;
+
+        tb.pin_person(surname, index);
+    }
+
+    let sort_by = get_sort_by(&tb, options.sort_by.as_ref())?;
+
+    let strategies = get_strategies(seed);
+    let strategy = strategies
+        .iter()
+        .find(|strategy| get_strategy_key(strategy.as_ref()) == options.strategy)
+        .ok_or_else(|| CliError::Usage(format!("unknown strategy '{}'", options.strategy)))?;
+
+    tb.calculate_teams_skill_level();
+    tb.sort_teams_by_skill_level(sort_by);
+
+    match options.layout {
+        TeamLayout::TeamSize(size) => {
+            tb.assign_people_with_strategy(strategy.as_ref(), size, sort_by)?
+        }
+        TeamLayout::Teams(teams) => {
+            tb.assign_people_to_number_of_teams(strategy.as_ref(), teams, sort_by)?
+        }
+        TeamLayout::SizeRange(min, max) => {
+            tb.assign_people_in_size_range(strategy.as_ref(), min, max, sort_by)?
+        }
+    }
+
+    if let Some((surname, &team)) = tb.pins.iter().find(|(_, &team)| team >= tb.teams.len()) {
+        return Err(CliError::Usage(format!(
+            "cannot pin '{}' to {}, only {} teams were made",
+            surname,
+            tb.get_team_title(team),
+            tb.teams.len()
+        )));
+    }
+
+    Ok(tb)
+}
+
+pub fn export(tb: &TeamBuilder, format: Format) -> String {
+    match format {
+        Format::Html => generate_html(tb).expect("Cannot generate HTML"),
+        Format::Json => generate_json(tb).expect("Cannot generate JSON"),
+        Format::Csv(delimiter) => generate_csv(tb, delimiter).expect("Cannot generate CSV"),
+        Format::Markdown => generate_markdown(tb),
+        Format::Text => generate_text(tb),
+    }
+}
+
+fn run_generate(args: &[String]) -> Result<i32, CliError> {
+    let options = parse_generate_args(args)?;
+    let tb = generate(&options)?;
+    let output = export(&tb, options.format);
+
+    match &options.output {
+        Some(path) => File::create(path)?.write_all(output.as_bytes())?,
+        None => io::stdout().write_all(output.as_bytes())?,
+    }
+
+    // The seed is not in every format, so it is always shown
+    if let Some(seed) = tb.seed {
+        eprintln!("Seed: {}", seed);
+    }
+
+    if tb.past_pairings.rounds > 0 {
+        let repeated = tb.get_repeated_pairings();
+
+        for pairing in &repeated {
+            eprintln!("note: {}", pairing);
+        }
+
+        eprintln!("{} repeated pairings", repeated.len());
+    }
+
+    let preference_report = tb.get_preference_report();
+
+    if !preference_report.is_empty() {
+        for satisfaction in &preference_report {
+            eprintln!("note: {}", satisfaction);
+        }
+
+        eprintln!(
+            "{:.0}% of the preferences granted",
+            tb.get_preference_satisfaction() * 100.0
+        );
+    }
+
+    let conflicts = tb.get_constraint_conflicts();
+
+    for conflict in &conflicts {
+        eprintln!("warning: {}", conflict);
+    }
+
+    // Only left when rules sharing people cannot all be met
+    let shortfalls = tb.get_coverage_shortfalls();
+
+    for shortfall in &shortfalls {
+        eprintln!("warning: {}", shortfall);
+    }
+
+    if !shortfalls.is_empty() {
+        Ok(EXIT_COVERAGE_NOT_MET)
+    } else if !conflicts.is_empty() {
+        Ok(EXIT_CONSTRAINT_CONFLICTS)
+    } else {
+        Ok(EXIT_OK)
+    }
+}
+
+fn run_check(args: &[String]) -> Result<i32, CliError> {
+    let input = match args {
+        [option, input] if option == "--input" => PathBuf::from(input),
+        _ => {
+            return Err(CliError::Usage(
+                "check expects --input <roster>".to_string(),
+            ))
+        }
+    };
+
+    let tb = load_roster(
+        &input,
+        CsvDialect::default(),
+        &ColumnNames::default(),
+        None,
+        None,
+    )?;
+
+    println!("{} people", tb.people.len());
+    println!("Skills: {}", tb.skills.join(", "));
+
+    Ok(EXIT_OK)
+}
+
+// Runs a subcommand and returns the exit code
+pub fn run(args: &[String]) -> i32 {
+    let result = match args.split_first() {
+        Some((command, args)) if command == "generate" => run_generate(args),
+        Some((command, args)) if command == "check" => run_check(args),
+        Some((command, _)) if command == "help" || command == "--help" => {
+            println!("{}", USAGE);
+            Ok(EXIT_OK)
+        }
+        Some((command, _)) => Err(CliError::Usage(format!("unknown command '{}'", command))),
+        None => Err(CliError::Usage("missing command".to_string())),
+    };
+
+    match result {
+        Ok(code) => code,
+        Err(err) => {
+            eprintln!("error: {}", err);
+
+            if let CliError::Usage(_) = err {
+                eprintln!("\n{}", USAGE);
+            }
+
+            err.get_exit_code()
+        }
+    }
+}
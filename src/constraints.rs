@@ -0,0 +1,193 @@
+use crate::builder::*;
+
+use csv::ReaderBuilder;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+// Added to the balancing cost for every broken pair,
+// so that satisfying the constraints always wins over skill balance
+pub const CONSTRAINT_PENALTY: f32 = 1_000_000.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConstraintKind {
+    Together,
+    Apart,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Constraint {
+    pub kind: ConstraintKind,
+    pub people: Vec<String>,
+}
+
+impl Constraint {
+    pub fn together(people: &[&str]) -> Self {
+        Self {
+            kind: ConstraintKind::Together,
+            people: people.iter().map(|surname| surname.to_string()).collect(),
+        }
+    }
+
+    pub fn apart(people: &[&str]) -> Self {
+        Self {
+            kind: ConstraintKind::Apart,
+            people: people.iter().map(|surname| surname.to_string()).collect(),
+        }
+    }
+
+    // Number of pairs of people that break the constraint
+    pub fn count_violations(&self, team_of: &HashMap<&str, usize>) -> usize {
+        let mut violations = 0;
+
+        for (index, first) in self.people.iter().enumerate() {
+            for second in self.people.iter().skip(index + 1) {
+                let first_team = team_of.get(first.as_str());
+                let second_team = team_of.get(second.as_str());
+
+                if first_team.is_none() || second_team.is_none() {
+                    continue;
+                }
+
+                let same_team = first_team == second_team;
+
+                match self.kind {
+                    ConstraintKind::Together if !same_team => violations += 1,
+                    ConstraintKind::Apart if same_team => violations += 1,
+                    _ => (),
+                }
+            }
+        }
+
+        violations
+    }
+}
+
+impl fmt::Display for Constraint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let kind = match self.kind {
+            ConstraintKind::Together => "together",
+            ConstraintKind::Apart => "apart",
+        };
+
+        write!(f, "{} must be {}", self.people.join(", "), kind)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConstraintConflict {
+    UnknownPerson {
+        constraint: Constraint,
+        surname: String,
+    },
+    Contradiction {
+        together: Constraint,
+        apart: Constraint,
+    },
+    // A group kept together that does not fit in the largest team
+    TooLarge {
+        constraint: Constraint,
+        largest_team: usize,
+    },
+    Unsatisfied(Constraint),
+}
+
+impl fmt::Display for ConstraintConflict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConstraintConflict::UnknownPerson {
+                constraint,
+                surname,
+            } => write!(f, "{}: nobody is called '{}'", constraint, surname),
+            ConstraintConflict::Contradiction { together, apart } => {
+                write!(f, "'{}' contradicts '{}'", together, apart)
+            }
+            ConstraintConflict::TooLarge {
+                constraint,
+                largest_team,
+            } => write!(
+                f,
+                "{}: the largest team has {} people",
+                constraint, largest_team
+            ),
+            ConstraintConflict::Unsatisfied(constraint) => {
+                write!(f, "{}: cannot be satisfied", constraint)
+            }
+        }
+    }
+}
+
+// Maps each surname to the index of its team
+pub fn get_team_of(teams: &[Team]) -> HashMap<&str, usize> {
+    let mut team_of = HashMap::new();
+
+    for (index, team) in teams.iter().enumerate() {
+        for person in &team.people {
+            team_of.insert(person.surname.as_str(), index);
+        }
+    }
+
+    team_of
+}
+
+pub fn count_violations(constraints: &[Constraint], teams: &[Team]) -> usize {
+    let team_of = get_team_of(teams);
+
+    constraints
+        .iter()
+        .map(|constraint| constraint.count_violations(&team_of))
+        .sum()
+}
+
+// The constraints for "roster.csv" live in "roster_constraints.csv"
+pub fn get_constraints_path(roster_path: &Path) -> PathBuf {
+    get_roster_sibling_path(roster_path, "constraints")
+}
+
+// After the header row, each row is "together" or "apart"
+// followed by two or more surnames
+pub fn parse_constraints(contents: &str) -> Result<Vec<Constraint>, TeamBuilderError> {
+    let mut constraints = Vec::new();
+
+    let mut reader = ReaderBuilder::new()
+        .delimiter(b';')
+        .flexible(true)
+        .from_reader(contents.as_bytes());
+
+    for record in reader.records() {
+        let record = record?;
+        let line = record.position().map_or(0, |position| position.line());
+
+        let kind = match record.get(0).map(|kind| kind.trim().to_lowercase()) {
+            Some(ref kind) if kind == "together" => ConstraintKind::Together,
+            Some(ref kind) if kind == "apart" => ConstraintKind::Apart,
+            kind => {
+                return Err(TeamBuilderError::Malformed {
+                    line,
+                    message: format!(
+                        "unknown constraint '{}', expected together or apart",
+                        kind.unwrap_or_default()
+                    ),
+                });
+            }
+        };
+
+        let people: Vec<String> = record
+            .iter()
+            .skip(1)
+            .map(|surname| surname.trim().to_string())
+            .filter(|surname| !surname.is_empty())
+            .collect();
+
+        if people.len() < 2 {
+            return Err(TeamBuilderError::Malformed {
+                line,
+                message: "a constraint needs at least two people".to_string(),
+            });
+        }
+
+        constraints.push(Constraint { kind, people });
+    }
+
+    Ok(constraints)
+}
@@ -0,0 +1,98 @@
+use crate::builder::*;
+
+use std::fmt;
+
+// Every team needs at least `count` people with `skill` at `level` or more
+#[derive(Debug, Clone, PartialEq)]
+pub struct CoverageRule {
+    pub skill: String,
+    pub level: u32,
+    pub count: usize,
+}
+
+impl CoverageRule {
+    // "Programming>=3" asks for one person per team,
+    // "2:Programming>=3" for two of them
+    pub fn parse(text: &str) -> Result<Self, TeamBuilderError> {
+        let malformed = || TeamBuilderError::Malformed {
+            line: 0,
+            message: format!(
+                "'{}' is not a coverage rule, expected [<count>:]<skill>>=<level>",
+                text
+            ),
+        };
+
+        let (count, rule) = match text.find(':') {
+            Some(position) => (
+                text[..position]
+                    .trim()
+                    .parse::<usize>()
+                    .map_err(|_| malformed())?,
+                &text[position + 1..],
+            ),
+            None => (1, text),
+        };
+
+        let position = rule.find(">=").ok_or_else(malformed)?;
+        let skill = rule[..position].trim();
+        let level = rule[position + 2..]
+            .trim()
+            .parse::<u32>()
+            .map_err(|_| malformed())?;
+
+        if skill.is_empty() || count == 0 {
+            return Err(malformed());
+        }
+
+        Ok(Self {
+            skill: skill.to_string(),
+            level,
+            count,
+        })
+    }
+}
+
+impl fmt::Display for CoverageRule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "at least {} with {} >= {}",
+            self.count, self.skill, self.level
+        )
+    }
+}
+
+// Rules separated by commas, as typed on the command line or in the UI
+pub fn parse_coverage_rules(text: &str) -> Result<Vec<CoverageRule>, TeamBuilderError> {
+    text.split(',')
+        .map(str::trim)
+        .filter(|rule| !rule.is_empty())
+        .map(CoverageRule::parse)
+        .collect()
+}
+
+// How many people of the team meet the rule, given the index of its skill
+pub fn count_qualified(people: &[Person], skill: usize, level: u32) -> usize {
+    people
+        .iter()
+        .filter(|person| person.skill_levels[skill] >= level)
+        .count()
+}
+
+// A team left with fewer qualified people than a rule asks for
+#[derive(Debug, Clone, PartialEq)]
+pub struct CoverageShortfall {
+    pub team: String,
+    pub rule: CoverageRule,
+    pub found: usize,
+}
+
+impl fmt::Display for CoverageShortfall {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} has {} of {} with {} >= {}",
+            self.team, self.found, self.rule.count, self.rule.skill, self.rule.level
+        )
+    }
+}
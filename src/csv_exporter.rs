@@ -0,0 +1,54 @@
+use crate::builder::*;
+
+use csv::WriterBuilder;
+
+// Delimiters offered in the UI, the first one is the default
+pub static CSV_DELIMITERS: [(&str, u8); 2] = [("Semicolon", b';'), ("Comma", b',')];
+
+// One row per person: team name, surname, categories, metadata,
+// skill levels and average.
+// People in reserve have "Reserve" as their team
+pub fn generate_csv(tb: &TeamBuilder, delimiter: u8) -> Option<String> {
+    let mut writer = WriterBuilder::new()
+        .delimiter(delimiter)
+        .from_writer(Vec::new());
+
+    let mut header = vec!["Team".to_string(), "Name".to_string()];
+    header.extend(tb.categories.iter().cloned());
+    header.extend(tb.metadata.iter().cloned());
+    header.extend(tb.skills.iter().cloned());
+    header.push("Average".to_string());
+
+    writer.write_record(&header).ok()?;
+
+    for (index, team) in tb.teams.iter().enumerate() {
+        let name = tb.get_team_title(index);
+
+        for person in &team.people {
+            writer.write_record(get_person_record(&name, person)).ok()?;
+        }
+    }
+
+    for person in &tb.reserve {
+        writer
+            .write_record(get_person_record("Reserve", person))
+            .ok()?;
+    }
+
+    let contents = writer.into_inner().ok()?;
+
+    String::from_utf8(contents).ok()
+}
+
+fn get_person_record(team: &str, person: &Person) -> Vec<String> {
+    let mut record = vec![team.to_string(), person.surname.clone()];
+    record.extend(person.categories.iter().cloned());
+    record.extend(person.metadata.iter().cloned());
+
+    for level in &person.skill_levels {
+        record.push(level.to_string());
+    }
+
+    record.push(format!("{:.2}", person.average_skill_level));
+    record
+}
@@ -0,0 +1,217 @@
+use crate::builder::*;
+use crate::json::*;
+
+use csv::ReaderBuilder;
+use std::collections::HashMap;
+use std::fmt;
+
+// Added to the balancing cost every time two people share a team again.
+// It competes with the variance of the team averages, which grows with
+// the skill scale, so TeamBuilder::repeat_weight scales it
+pub const REPEAT_PENALTY: f32 = 0.1;
+
+// How many earlier rounds each pair of people spent in the same team
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct PastPairings {
+    pub rounds: usize,
+    // Stored both ways, so either surname finds the other
+    pairs: HashMap<String, HashMap<String, usize>>,
+}
+
+impl PastPairings {
+    // Each team is the list of surnames of its people
+    pub fn add_round(&mut self, teams: &[Vec<String>]) {
+        for team in teams {
+            for (index, first) in team.iter().enumerate() {
+                for second in &team[index + 1..] {
+                    self.add_pair(first, second);
+                    self.add_pair(second, first);
+                }
+            }
+        }
+
+        self.rounds += 1;
+    }
+
+    fn add_pair(&mut self, first: &str, second: &str) {
+        *self
+            .pairs
+            .entry(first.to_string())
+            .or_default()
+            .entry(second.to_string())
+            .or_insert(0) += 1;
+    }
+
+    pub fn get_count(&self, first: &str, second: &str) -> usize {
+        self.pairs
+            .get(first)
+            .and_then(|others| others.get(second))
+            .cloned()
+            .unwrap_or(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pairs.is_empty()
+    }
+
+    // Number of different pairs that shared a team at least once
+    pub fn count_pairs(&self) -> usize {
+        self.pairs
+            .values()
+            .map(|others| others.len())
+            .sum::<usize>()
+            / 2
+    }
+
+    // Every earlier round two people of the same team already shared
+    pub fn count_repeats(&self, teams: &[Team]) -> usize {
+        if self.is_empty() {
+            return 0;
+        }
+
+        let mut repeats = 0;
+
+        for team in teams {
+            for (index, first) in team.people.iter().enumerate() {
+                for second in &team.people[index + 1..] {
+                    repeats += self.get_count(&first.surname, &second.surname);
+                }
+            }
+        }
+
+        repeats
+    }
+
+    // The pairs of each team that already worked together,
+    // in the order of the teams
+    pub fn get_repeated_pairings(&self, teams: &[Team]) -> Vec<RepeatedPairing> {
+        let mut repeated = Vec::new();
+
+        for team in teams {
+            for (index, first) in team.people.iter().enumerate() {
+                for second in &team.people[index + 1..] {
+                    let rounds = self.get_count(&first.surname, &second.surname);
+
+                    if rounds > 0 {
+                        repeated.push(RepeatedPairing {
+                            first: first.surname.clone(),
+                            second: second.surname.clone(),
+                            rounds,
+                        });
+                    }
+                }
+            }
+        }
+
+        repeated
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RepeatedPairing {
+    pub first: String,
+    pub second: String,
+    // Earlier rounds they were already together
+    pub rounds: usize,
+}
+
+impl fmt::Display for RepeatedPairing {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} and {} were already together in {} ",
+            self.first, self.second, self.rounds
+        )?;
+
+        if self.rounds == 1 {
+            write!(f, "round")
+        } else {
+            write!(f, "rounds")
+        }
+    }
+}
+
+// The surnames of the people in each team
+pub fn get_round(teams: &[Team]) -> Vec<Vec<String>> {
+    teams
+        .iter()
+        .map(|team| {
+            team.people
+                .iter()
+                .map(|person| person.surname.clone())
+                .collect()
+        })
+        .collect()
+}
+
+// Reads the teams back from a CSV export: the "Team" and "Name" columns
+// are used and the people in reserve are left out
+pub fn parse_round_csv(contents: &str) -> Result<Vec<Vec<String>>, TeamBuilderError> {
+    let contents = contents.trim_start_matches('\u{feff}');
+
+    let mut reader = ReaderBuilder::new()
+        .delimiter(sniff_delimiter(contents))
+        .flexible(true)
+        .from_reader(contents.as_bytes());
+
+    let headers = reader.headers()?.clone();
+    let find_column = |name: &str| {
+        headers
+            .iter()
+            .position(|header| header.trim().eq_ignore_ascii_case(name))
+    };
+
+    let (team_column, name_column) = match (find_column("Team"), find_column("Name")) {
+        (Some(team), Some(name)) => (team, name),
+        _ => return Err(TeamBuilderError::MissingHeader),
+    };
+
+    let mut team_names: Vec<String> = Vec::new();
+    let mut teams: Vec<Vec<String>> = Vec::new();
+
+    for record in reader.records() {
+        let record = record?;
+        let line = record.position().map_or(0, |position| position.line());
+
+        let (team, name) = match (record.get(team_column), record.get(name_column)) {
+            (Some(team), Some(name)) => (team.trim(), name.trim()),
+            _ => {
+                return Err(TeamBuilderError::Malformed {
+                    line,
+                    message: "expected a team and a name".to_string(),
+                });
+            }
+        };
+
+        if team.eq_ignore_ascii_case("Reserve") {
+            continue;
+        }
+
+        match team_names.iter().position(|other| other == team) {
+            Some(index) => teams[index].push(name.to_string()),
+            None => {
+                team_names.push(team.to_string());
+                teams.push(vec![name.to_string()]);
+            }
+        }
+    }
+
+    Ok(teams)
+}
+
+// Reads the teams back from a JSON export
+pub fn parse_round_json(contents: &str) -> Result<Vec<Vec<String>>, TeamBuilderError> {
+    let snapshot: TeamBuilderSnapshot =
+        serde_json::from_str(contents.trim_start_matches('\u{feff}'))?;
+
+    Ok(snapshot
+        .teams
+        .iter()
+        .map(|team| {
+            team.people
+                .iter()
+                .map(|person| person.surname.clone())
+                .collect()
+        })
+        .collect())
+}
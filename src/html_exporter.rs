@@ -1,4 +1,5 @@
 use crate::builder::*;
+use crate::history::*;
 
 const HTML_HEADER: &str = "<!DOCTYPE html>
 <html>
@@ -27,31 +28,215 @@ const HTML_FOOTER: &str = "</body>
 </html>";
 
 // TODO: Export as an external crate
-// TODO: Add missing cells if empty
 
-pub fn generate_html(teams: &[Team]) -> Option<String> {
+pub fn generate_html(tb: &TeamBuilder) -> Option<String> {
   let mut html = String::new();
 
   html.push_str(HTML_HEADER);
 
+  push_summary(&mut html, tb);
+
+  for (index, team) in tb.teams.iter().enumerate() {
+    html.push_str(&format!(
+      "<h2>{}</h2>",
+      escape_html(&tb.get_team_title(index))
+    ));
+    push_people_table(&mut html, tb, &team.people, Some(team));
+  }
+
+  if !tb.reserve.is_empty() {
+    html.push_str("<h2>Reserve</h2>");
+    push_people_table(&mut html, tb, &tb.reserve, None);
+  }
+
+  html.push_str(HTML_FOOTER);
+
+  Some(html)
+}
+
+// Every round of a schedule on one page, each one with the number
+// of pairings already seen in the rounds before it
+pub fn generate_schedule_html(tb: &TeamBuilder, schedule: &[Vec<Team>]) -> Option<String> {
+  let mut html = String::new();
+
+  html.push_str(HTML_HEADER);
+  html.push_str("<h1>Team Builder</h1>");
+
+  let mut schedule_pairings = PastPairings::default();
+
+  for teams in schedule {
+    schedule_pairings.add_round(&get_round(teams));
+  }
+
   html.push_str("<table>");
+  push_summary_row(&mut html, "People", &tb.people.len().to_string());
+  push_summary_row(&mut html, "Rounds", &schedule.len().to_string());
+  push_summary_row(
+    &mut html,
+    "Different pairings",
+    &schedule_pairings.count_pairs().to_string(),
+  );
+  html.push_str("</table>");
 
-  for team in teams {
-    html.push_str("<tr>");
+  let mut past_pairings = tb.past_pairings.clone();
 
-    // TODO: Remove hardcoded team name
-    html.push_str(&format!("<th>{}</th>", "Team name"));
+  for (round, teams) in schedule.iter().enumerate() {
+    html.push_str(&format!("<h2>Round {}</h2>", round + 1));
+    html.push_str(&format!(
+      "<p>Repeated pairings: {}</p>",
+      past_pairings.get_repeated_pairings(teams).len()
+    ));
 
-    for person in &team.people {
-      html.push_str(&format!("<td>{}</td>", &person.surname));
+    for (index, team) in teams.iter().enumerate() {
+      html.push_str(&format!(
+        "<h3>{}</h3>",
+        escape_html(&tb.get_team_title(index))
+      ));
+      push_people_table(&mut html, tb, &team.people, Some(team));
     }
 
-    html.push_str("</tr>");
+    past_pairings.add_round(&get_round(teams));
   }
 
-  html.push_str("</table>");
+  if !tb.reserve.is_empty() {
+    html.push_str("<h2>Reserve</h2>");
+    push_people_table(&mut html, tb, &tb.reserve, None);
+  }
 
   html.push_str(HTML_FOOTER);
 
   Some(html)
 }
+
+// Escapes the characters that would otherwise be read as markup
+pub fn escape_html(text: &str) -> String {
+  let mut escaped = String::with_capacity(text.len());
+
+  for character in text.chars() {
+    match character {
+      '&' => escaped.push_str("&amp;"),
+      '<' => escaped.push_str("&lt;"),
+      '>' => escaped.push_str("&gt;"),
+      '"' => escaped.push_str("&quot;"),
+      '\'' => escaped.push_str("&#39;"),
+      _ => escaped.push(character),
+    }
+  }
+
+  escaped
+}
+
+// Roster-wide numbers, before the teams
+fn push_summary(html: &mut String, tb: &TeamBuilder) {
+  html.push_str("<h1>Team Builder</h1>");
+
+  html.push_str("<table>");
+  push_summary_row(html, "People", &tb.people.len().to_string());
+  push_summary_row(html, "Teams", &tb.teams.len().to_string());
+
+  if !tb.reserve.is_empty() {
+    push_summary_row(html, "Reserve", &tb.reserve.len().to_string());
+  }
+
+  let roster = Team {
+    people: tb.people.clone(),
+  };
+
+  for (index, skill) in tb.skills.iter().enumerate() {
+    let average = roster.get_average_skill_level(Some(index));
+    push_summary_row(html, skill, &format!("{:.2}", average));
+  }
+
+  let average = roster.get_average_skill_level(None);
+  push_summary_row(html, "Average skill level", &format!("{:.2}", average));
+
+  if tb
+    .people
+    .iter()
+    .any(|person| !person.preferences.is_empty())
+  {
+    let satisfaction = tb.get_preference_satisfaction() * 100.0;
+    push_summary_row(
+      html,
+      "Preferences granted",
+      &format!("{:.0}%", satisfaction),
+    );
+  }
+
+  if tb.past_pairings.rounds > 0 {
+    let repeated = tb.get_repeated_pairings().len();
+    push_summary_row(html, "Repeated pairings", &repeated.to_string());
+  }
+  html.push_str("</table>");
+
+  html.push_str(&format!(
+    "<p>{}</p>",
+    escape_html(&tb.remainder_policy.to_string())
+  ));
+
+  if let Some(seed) = tb.seed {
+    html.push_str(&format!("<p>Seed: {}</p>", seed));
+  }
+}
+
+fn push_summary_row(html: &mut String, name: &str, value: &str) {
+  html.push_str(&format!(
+    "<tr><th>{}</th><td>{}</td></tr>",
+    escape_html(name),
+    escape_html(value)
+  ));
+}
+
+// One row per person with their categories, metadata and skill levels,
+// followed by the team averages
+fn push_people_table(html: &mut String, tb: &TeamBuilder, people: &[Person], team: Option<&Team>) {
+  html.push_str("<table>");
+
+  html.push_str("<tr><th>Name</th>");
+
+  for name in tb.categories.iter().chain(&tb.metadata) {
+    html.push_str(&format!("<th>{}</th>", escape_html(name)));
+  }
+
+  for skill in &tb.skills {
+    html.push_str(&format!("<th>{}</th>", escape_html(skill)));
+  }
+
+  html.push_str("<th>Average</th></tr>");
+
+  for person in people {
+    html.push_str(&format!("<tr><td>{}</td>", escape_html(&person.surname)));
+
+    for value in person.categories.iter().chain(&person.metadata) {
+      html.push_str(&format!("<td>{}</td>", escape_html(value)));
+    }
+
+    for level in &person.skill_levels {
+      html.push_str(&format!("<td>{}</td>", level));
+    }
+
+    html.push_str(&format!("<td>{:.2}</td></tr>", person.average_skill_level));
+  }
+
+  if let Some(team) = team {
+    html.push_str("<tr><th>Average</th>");
+
+    for _ in tb.categories.iter().chain(&tb.metadata) {
+      html.push_str("<td></td>");
+    }
+
+    for index in 0..tb.skills.len() {
+      html.push_str(&format!(
+        "<td>{:.2}</td>",
+        team.get_average_skill_level(Some(index))
+      ));
+    }
+
+    html.push_str(&format!(
+      "<td>{:.2}</td></tr>",
+      team.get_average_skill_level(None)
+    ));
+  }
+
+  html.push_str("</table>");
+}
@@ -0,0 +1,165 @@
+use crate::builder::*;
+use crate::preferences::*;
+
+use serde_derive::{Deserialize, Serialize};
+use std::path::Path;
+
+// A roster as read from JSON, the counterpart of the CSV file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Roster {
+    pub skills: Vec<String>,
+    // Every skill counts the same when left out
+    #[serde(default)]
+    pub weights: Vec<f32>,
+    // Names of the values in each person's categories
+    #[serde(default)]
+    pub categories: Vec<String>,
+    // Names of the values in each person's metadata
+    #[serde(default)]
+    pub metadata: Vec<String>,
+    pub people: Vec<Person>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TeamSnapshot {
+    pub name: String,
+    pub average_skill_level: f32,
+    // In the same order as the skills
+    pub skill_averages: Vec<f32>,
+    pub people: Vec<Person>,
+}
+
+// The generated teams with everything needed to show them
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TeamBuilderSnapshot {
+    pub skills: Vec<String>,
+    pub skill_weights: Vec<f32>,
+    pub categories: Vec<String>,
+    pub metadata: Vec<String>,
+    pub remainder_policy: RemainderPolicy,
+    pub seed: Option<u64>,
+    pub teams: Vec<TeamSnapshot>,
+    pub reserve: Vec<Person>,
+}
+
+impl From<&TeamBuilder> for TeamBuilderSnapshot {
+    fn from(tb: &TeamBuilder) -> Self {
+        let teams = tb
+            .teams
+            .iter()
+            .enumerate()
+            .map(|(index, team)| TeamSnapshot {
+                name: tb.get_team_title(index),
+                average_skill_level: team.get_average_skill_level(None),
+                skill_averages: (0..tb.skills.len())
+                    .map(|skill| team.get_average_skill_level(Some(skill)))
+                    .collect(),
+                people: team.people.clone(),
+            })
+            .collect();
+
+        Self {
+            skills: tb.skills.clone(),
+            skill_weights: tb.skill_weights.clone(),
+            categories: tb.categories.clone(),
+            metadata: tb.metadata.clone(),
+            remainder_policy: tb.remainder_policy,
+            seed: tb.seed,
+            teams,
+            reserve: tb.reserve.clone(),
+        }
+    }
+}
+
+// Rosters ending in .json are read as JSON, everything else as CSV
+pub fn is_json_path(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("json"))
+}
+
+// Checks the same things as TeamBuilder::process_file, the rows
+// reported in the errors are the positions in the people list,
+// or 0 for the weights
+pub fn parse_roster(contents: &str) -> Result<Roster, TeamBuilderError> {
+    let roster: Roster = serde_json::from_str(contents.trim_start_matches('\u{feff}'))?;
+
+    if roster.skills.is_empty() {
+        return Err(TeamBuilderError::MissingHeader);
+    }
+
+    if !roster.weights.is_empty() && roster.weights.len() != roster.skills.len() {
+        return Err(TeamBuilderError::Malformed {
+            line: 0,
+            message: format!(
+                "expected {} weights, found {}",
+                roster.skills.len(),
+                roster.weights.len()
+            ),
+        });
+    }
+
+    check_weights(&roster.skills, &roster.weights)?;
+
+    for (index, person) in roster.people.iter().enumerate() {
+        if person.skill_levels.len() != roster.skills.len() {
+            return Err(TeamBuilderError::Malformed {
+                line: index as u64 + 1,
+                message: format!(
+                    "'{}' has {} skill levels, expected {}",
+                    person.surname,
+                    person.skill_levels.len(),
+                    roster.skills.len()
+                ),
+            });
+        }
+
+        if person.categories.len() != roster.categories.len() {
+            return Err(TeamBuilderError::Malformed {
+                line: index as u64 + 1,
+                message: format!(
+                    "'{}' has {} categories, expected {}",
+                    person.surname,
+                    person.categories.len(),
+                    roster.categories.len()
+                ),
+            });
+        }
+
+        if person.metadata.len() != roster.metadata.len() {
+            return Err(TeamBuilderError::Malformed {
+                line: index as u64 + 1,
+                message: format!(
+                    "'{}' has {} metadata values, expected {}",
+                    person.surname,
+                    person.metadata.len(),
+                    roster.metadata.len()
+                ),
+            });
+        }
+
+        if person.preferences.len() > MAX_PREFERENCES {
+            return Err(TeamBuilderError::Malformed {
+                line: index as u64 + 1,
+                message: format!(
+                    "'{}' has {} preferences, at most {} are allowed",
+                    person.surname,
+                    person.preferences.len(),
+                    MAX_PREFERENCES
+                ),
+            });
+        }
+
+        check_preferences(
+            &roster.people,
+            &person.surname,
+            &person.preferences,
+            index as u64 + 1,
+        )?;
+    }
+
+    Ok(roster)
+}
+
+pub fn generate_json(tb: &TeamBuilder) -> Option<String> {
+    serde_json::to_string_pretty(&TeamBuilderSnapshot::from(tb)).ok()
+}
@@ -0,0 +1,37 @@
+// #![warn(clippy::all, clippy::pedantic, clippy::nursery)]
+
+extern crate csv;
+extern crate itertools;
+#[cfg(feature = "gui")]
+extern crate iui;
+extern crate serde_derive;
+extern crate serde_json;
+extern crate simple_excel_writer as excel;
+extern crate strum;
+extern crate strum_macros;
+
+pub mod builder;
+pub mod cli;
+pub mod constraints;
+pub mod coverage;
+pub mod csv_exporter;
+pub mod history;
+pub mod html_exporter;
+pub mod json;
+pub mod preferences;
+pub mod random;
+pub mod spreadsheet_exporter;
+pub mod strategy;
+pub mod text_exporter;
+#[cfg(feature = "gui")]
+pub mod ui;
+
+#[cfg(test)]
+pub mod tests;
+
+pub use builder::{Person, Team, TeamBuilder, TeamBuilderError};
+pub use csv_exporter::generate_csv;
+pub use html_exporter::{generate_html, generate_schedule_html};
+pub use json::generate_json;
+pub use spreadsheet_exporter::{generate_schedule_spreadsheet, generate_spreadsheet};
+pub use text_exporter::{generate_markdown, generate_text};
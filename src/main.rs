@@ -1,34 +1,37 @@
 // #![warn(clippy::all, clippy::pedantic, clippy::nursery)]
 
-extern crate csv;
-extern crate itertools;
-extern crate iui;
-extern crate serde_derive;
-extern crate simple_excel_writer as excel;
-extern crate strum;
-extern crate strum_macros;
-
-pub mod builder;
-pub mod html_exporter;
-pub mod spreadsheet_exporter;
-pub mod ui;
-
-#[cfg(test)]
-pub mod tests;
-
-use builder::*;
-#[allow(unused_imports)]
-use std::path::Path;
-use ui::*;
+use team_builder::cli;
+#[cfg(feature = "gui")]
+use team_builder::ui::*;
+#[cfg(feature = "gui")]
+use team_builder::TeamBuilder;
 
+#[cfg(feature = "gui")]
 use std::cell::RefCell;
+use std::env;
+use std::process;
+#[cfg(feature = "gui")]
 use std::rc::Rc;
 
 // TODO: Add more teams
-// TODO: Warn user when teams would overflow
 // TODO: Update people values based on their skill
 
+#[cfg(feature = "gui")]
 fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    // Any argument runs the command line interface instead of the window
+    if !args.is_empty() {
+        process::exit(cli::run(&args));
+    }
+
     let tb = Rc::new(RefCell::new(TeamBuilder::new()));
     init_ui(&tb);
 }
+
+// Without the window only the command line interface is available
+#[cfg(not(feature = "gui"))]
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    process::exit(cli::run(&args));
+}
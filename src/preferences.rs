@@ -0,0 +1,152 @@
+use crate::builder::*;
+use crate::constraints::*;
+
+use csv::ReaderBuilder;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+// How many people each person can ask to work with
+pub const MAX_PREFERENCES: usize = 3;
+
+// Added to the balancing cost for every preference left out, low enough
+// that skill balance stays the main goal. TeamBuilder::preference_weight
+// scales it
+pub const PREFERENCE_PENALTY: f32 = 0.01;
+
+// The preferences for "roster.csv" live in "roster_preferences.csv"
+pub fn get_preferences_path(roster_path: &Path) -> PathBuf {
+    get_roster_sibling_path(roster_path, "preferences")
+}
+
+// After the header row, each row is a surname followed by
+// the people they would like to work with
+pub fn parse_preferences(contents: &str) -> Result<Vec<(String, Vec<String>)>, TeamBuilderError> {
+    let contents = contents.trim_start_matches('\u{feff}');
+    let mut preferences = Vec::new();
+
+    let mut reader = ReaderBuilder::new()
+        .delimiter(sniff_delimiter(contents))
+        .flexible(true)
+        .from_reader(contents.as_bytes());
+
+    for record in reader.records() {
+        let record = record?;
+        let line = record.position().map_or(0, |position| position.line());
+
+        let surname = match record.get(0).map(str::trim) {
+            Some(surname) if !surname.is_empty() => surname.to_string(),
+            _ => {
+                return Err(TeamBuilderError::Malformed {
+                    line,
+                    message: "expected a name in the first column".to_string(),
+                });
+            }
+        };
+
+        let wanted = get_preferences(record.iter().skip(1), line)?;
+        preferences.push((surname, wanted));
+    }
+
+    Ok(preferences)
+}
+
+// The names that are not empty, at most MAX_PREFERENCES of them
+pub fn get_preferences<'a, I>(values: I, line: u64) -> Result<Vec<String>, TeamBuilderError>
+where
+    I: Iterator<Item = &'a str>,
+{
+    let wanted: Vec<String> = values
+        .map(str::trim)
+        .filter(|surname| !surname.is_empty())
+        .map(str::to_string)
+        .collect();
+
+    if wanted.len() > MAX_PREFERENCES {
+        return Err(TeamBuilderError::Malformed {
+            line,
+            message: format!(
+                "at most {} preferences are allowed, found {}",
+                MAX_PREFERENCES,
+                wanted.len()
+            ),
+        });
+    }
+
+    Ok(wanted)
+}
+
+// Every name must be somebody in the roster, otherwise a typo
+// would never be granted and nobody would notice
+pub fn check_preferences(
+    people: &[Person],
+    surname: &str,
+    wanted: &[String],
+    line: u64,
+) -> Result<(), TeamBuilderError> {
+    match wanted
+        .iter()
+        .find(|target| !people.iter().any(|person| &person.surname == *target))
+    {
+        Some(target) => Err(TeamBuilderError::Malformed {
+            line,
+            message: format!(
+                "'{}' prefers '{}', but nobody is called that",
+                surname, target
+            ),
+        }),
+        None => Ok(()),
+    }
+}
+
+// Preferences of the people in the teams that are not in their team
+pub fn count_unmet_preferences(teams: &[Team]) -> usize {
+    let has_preferences = teams
+        .iter()
+        .flat_map(|team| &team.people)
+        .any(|person| !person.preferences.is_empty());
+
+    if !has_preferences {
+        return 0;
+    }
+
+    let team_of = get_team_of(teams);
+    let mut unmet = 0;
+
+    for (index, team) in teams.iter().enumerate() {
+        for person in &team.people {
+            unmet += person
+                .preferences
+                .iter()
+                .filter(|surname| team_of.get(surname.as_str()) != Some(&index))
+                .count();
+        }
+    }
+
+    unmet
+}
+
+// Which of the people someone asked for ended up in their team
+#[derive(Debug, Clone, PartialEq)]
+pub struct PreferenceSatisfaction {
+    pub surname: String,
+    pub wanted: Vec<String>,
+    pub granted: Vec<String>,
+}
+
+impl fmt::Display for PreferenceSatisfaction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} of {}",
+            self.surname,
+            self.granted.len(),
+            self.wanted.len()
+        )?;
+
+        if !self.granted.is_empty() {
+            write!(f, " ({})", self.granted.join(", "))?;
+        }
+
+        Ok(())
+    }
+}
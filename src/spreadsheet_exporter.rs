@@ -1,30 +1,172 @@
 use crate::builder::*;
 
 use excel::*;
+use std::fs::File;
+use std::io::prelude::*;
+use std::path::Path;
 
-pub fn generate_spreadsheet(teams: &[Team]) {
-    // TODO: Remove hardcoded values
-    let mut workbook = excel::Workbook::create("test.xlsx");
-    let mut sheet = workbook.create_sheet("test_sheet");
+const NAME_COLUMN_WIDTH: f32 = 20.0;
+const SKILL_COLUMN_WIDTH: f32 = 12.0;
 
-    // TODO: Add column padding
-    workbook
-        .write_sheet(&mut sheet, |data| {
-            for team in teams {
-                let mut row = Row::new();
+pub fn generate_spreadsheet(tb: &TeamBuilder, path: &Path) -> Result<(), TeamBuilderError> {
+    // The workbook is built in memory, as saving it directly
+    // panics when the file cannot be created
+    let contents = get_spreadsheet(tb)?;
+    File::create(path)?.write_all(&contents)?;
 
-                // TODO: Remove hardcoded team name
-                row.add_cell("Team name");
+    Ok(())
+}
+
+// A single sheet with a block per team: the people with their
+// skill levels and average, then the team totals and averages
+pub fn get_spreadsheet(tb: &TeamBuilder) -> Result<Vec<u8>, TeamBuilderError> {
+    let mut workbook = excel::Workbook::create_in_memory();
+    write_teams_sheet(&mut workbook, "Teams", tb, &tb.teams, &tb.reserve)?;
+
+    let contents = workbook.close()?.unwrap_or_default();
 
-                for person in &team.people {
-                    row.add_cell(person.surname.as_str());
-                }
+    Ok(contents)
+}
+
+pub fn generate_schedule_spreadsheet(
+    tb: &TeamBuilder,
+    schedule: &[Vec<Team>],
+    path: &Path,
+) -> Result<(), TeamBuilderError> {
+    let contents = get_schedule_spreadsheet(tb, schedule)?;
+    File::create(path)?.write_all(&contents)?;
 
-                data.append_row(row).unwrap();
+    Ok(())
+}
+
+// The same blocks as get_spreadsheet, with a sheet per round
+pub fn get_schedule_spreadsheet(
+    tb: &TeamBuilder,
+    schedule: &[Vec<Team>],
+) -> Result<Vec<u8>, TeamBuilderError> {
+    let mut workbook = excel::Workbook::create_in_memory();
+
+    for (index, teams) in schedule.iter().enumerate() {
+        let name = format!("Round {}", index + 1);
+        write_teams_sheet(&mut workbook, &name, tb, teams, &tb.reserve)?;
+    }
+
+    let contents = workbook.close()?.unwrap_or_default();
+
+    Ok(contents)
+}
+
+fn write_teams_sheet(
+    workbook: &mut Workbook,
+    name: &str,
+    tb: &TeamBuilder,
+    teams: &[Team],
+    reserve: &[Person],
+) -> Result<(), TeamBuilderError> {
+    let mut sheet = workbook.create_sheet(name);
+
+    sheet.add_column(Column {
+        width: NAME_COLUMN_WIDTH,
+    });
+
+    // One column per category, metadata and skill plus the average
+    for _ in 0..=tb.categories.len() + tb.metadata.len() + tb.skills.len() {
+        sheet.add_column(Column {
+            width: SKILL_COLUMN_WIDTH,
+        });
+    }
+
+    workbook.write_sheet(&mut sheet, |data| {
+        data.append_row(row![tb.remainder_policy.to_string()])?;
+
+        if let Some(seed) = tb.seed {
+            data.append_row(row!["Seed", seed.to_string()])?;
+        }
+
+        for (index, team) in teams.iter().enumerate() {
+            data.append_blank_rows(1);
+
+            data.append_row(get_header_row(&tb.get_team_title(index), tb))?;
+
+            for person in &team.people {
+                data.append_row(get_person_row(person))?;
             }
-            Ok(())
-        })
-        .expect("Write Excel error!");
 
-    workbook.close().expect("Close Excel error!");
+            let mut total_row = Row::new();
+            total_row.add_cell("Total");
+            total_row.add_empty_cells(tb.categories.len() + tb.metadata.len());
+
+            let mut average_row = Row::new();
+            average_row.add_cell("Average");
+            average_row.add_empty_cells(tb.categories.len() + tb.metadata.len());
+
+            for skill in 0..tb.skills.len() {
+                let total: u32 = team
+                    .people
+                    .iter()
+                    .map(|person| person.skill_levels[skill])
+                    .sum();
+
+                total_row.add_cell(f64::from(total));
+                average_row.add_cell(f64::from(team.get_average_skill_level(Some(skill))));
+            }
+
+            let total: f32 = team
+                .people
+                .iter()
+                .map(|person| person.average_skill_level)
+                .sum();
+
+            total_row.add_cell(f64::from(total));
+            average_row.add_cell(f64::from(team.get_average_skill_level(None)));
+
+            data.append_row(total_row)?;
+            data.append_row(average_row)?;
+        }
+
+        if !reserve.is_empty() {
+            data.append_blank_rows(1);
+            data.append_row(get_header_row("Reserve", tb))?;
+
+            for person in reserve {
+                data.append_row(get_person_row(person))?;
+            }
+        }
+
+        Ok(())
+    })?;
+
+    Ok(())
+}
+
+fn get_header_row(name: &str, tb: &TeamBuilder) -> Row {
+    let mut row = Row::new();
+    row.add_cell(name);
+
+    for name in tb.categories.iter().chain(&tb.metadata) {
+        row.add_cell(name.as_str());
+    }
+
+    for skill in &tb.skills {
+        row.add_cell(skill.as_str());
+    }
+
+    row.add_cell("Average");
+    row
+}
+
+fn get_person_row(person: &Person) -> Row {
+    let mut row = Row::new();
+    row.add_cell(person.surname.as_str());
+
+    for value in person.categories.iter().chain(&person.metadata) {
+        row.add_cell(value.as_str());
+    }
+
+    for level in &person.skill_levels {
+        row.add_cell(f64::from(*level));
+    }
+
+    row.add_cell(f64::from(person.average_skill_level));
+    row
 }
@@ -0,0 +1,260 @@
+use crate::builder::*;
+use crate::random::*;
+
+// Ignore improvements caused by floating point noise
+const COST_EPSILON: f32 = 1e-6;
+
+pub trait AssignmentStrategy {
+    fn name(&self) -> &str;
+
+    // People are sorted from the lowest to the greatest score, as done by
+    // sort_teams_by_skill_level. Each team must get exactly as many
+    // people as its size, and the sizes add up to the number of people.
+    // Pinned people are not given, they are already in their team
+    fn assign(
+        &self,
+        tb: &TeamBuilder,
+        people: &[Person],
+        team_sizes: &[usize],
+        sort_by: Option<usize>,
+    ) -> Vec<Team>;
+}
+
+// Goes back and forth over the teams, picking the best people first.
+// With pinned people the weakest team picks next instead
+pub struct SnakeDraft;
+
+impl AssignmentStrategy for SnakeDraft {
+    fn name(&self) -> &str {
+        "Snake draft"
+    }
+
+    fn assign(
+        &self,
+        tb: &TeamBuilder,
+        people: &[Person],
+        team_sizes: &[usize],
+        sort_by: Option<usize>,
+    ) -> Vec<Team> {
+        let pinned = tb.get_pinned_teams(team_sizes.len());
+
+        if pinned.iter().any(|team| !team.people.is_empty()) {
+            return fill_weakest_first(&pinned, people, team_sizes, sort_by);
+        }
+
+        let forward = 0..team_sizes.len();
+        let backward = forward.clone().rev();
+
+        fill_teams(
+            people.iter().rev().cloned(),
+            team_sizes,
+            forward.chain(backward).cycle(),
+        )
+    }
+}
+
+// Always goes from the first to the last team, picking the best people first
+pub struct RoundRobin;
+
+impl AssignmentStrategy for RoundRobin {
+    fn name(&self) -> &str {
+        "Round robin"
+    }
+
+    fn assign(
+        &self,
+        _: &TeamBuilder,
+        people: &[Person],
+        team_sizes: &[usize],
+        _: Option<usize>,
+    ) -> Vec<Team> {
+        fill_teams(
+            people.iter().rev().cloned(),
+            team_sizes,
+            (0..team_sizes.len()).cycle(),
+        )
+    }
+}
+
+// Ignores skills altogether
+pub struct RandomShuffle {
+    pub seed: u64,
+}
+
+impl AssignmentStrategy for RandomShuffle {
+    fn name(&self) -> &str {
+        "Random shuffle"
+    }
+
+    fn assign(
+        &self,
+        _: &TeamBuilder,
+        people: &[Person],
+        team_sizes: &[usize],
+        _: Option<usize>,
+    ) -> Vec<Team> {
+        let mut people = people.to_vec();
+        Random::new(self.seed).shuffle(&mut people);
+
+        fill_teams(
+            people.into_iter(),
+            team_sizes,
+            (0..team_sizes.len()).cycle(),
+        )
+    }
+}
+
+// Starts from the snake draft and swaps people until the teams
+// are as balanced as possible while respecting the constraints
+pub struct Optimizing {
+    pub all_skills: bool,
+}
+
+impl AssignmentStrategy for Optimizing {
+    fn name(&self) -> &str {
+        if self.all_skills {
+            "Optimized (all skills)"
+        } else {
+            "Optimized"
+        }
+    }
+
+    fn assign(
+        &self,
+        tb: &TeamBuilder,
+        people: &[Person],
+        team_sizes: &[usize],
+        sort_by: Option<usize>,
+    ) -> Vec<Team> {
+        let mut teams = SnakeDraft.assign(tb, people, team_sizes, sort_by);
+
+        // The pinned people are not swapped but count in the cost
+        let pinned = tb.get_pinned_teams(team_sizes.len());
+
+        if self.all_skills {
+            local_search(&mut teams, |teams| {
+                tb.get_teams_cost_all_skills(&merge_teams(&pinned, teams))
+            });
+        } else {
+            local_search(&mut teams, |teams| {
+                tb.get_teams_cost(&merge_teams(&pinned, teams), sort_by)
+            });
+        }
+
+        teams
+    }
+}
+
+// Strategies listed in the UI, the first one is the default
+pub fn get_strategies(seed: u64) -> Vec<Box<dyn AssignmentStrategy>> {
+    vec![
+        Box::new(Optimizing { all_skills: false }),
+        Box::new(Optimizing { all_skills: true }),
+        Box::new(SnakeDraft),
+        Box::new(RoundRobin),
+        Box::new(RandomShuffle { seed }),
+    ]
+}
+
+// Gives each person to the next team in the order that is not full yet
+fn fill_teams<P, O>(people: P, team_sizes: &[usize], mut order: O) -> Vec<Team>
+where
+    P: ExactSizeIterator<Item = Person>,
+    O: Iterator<Item = usize>,
+{
+    assert_eq!(
+        people.len(),
+        team_sizes.iter().sum::<usize>(),
+        "Team sizes must add up to the number of people"
+    );
+
+    let mut teams = vec![Team::default(); team_sizes.len()];
+
+    for person in people {
+        let index = order
+            .find(|&index| teams[index].people.len() < team_sizes[index])
+            .expect("Cannot find a team with a free place");
+
+        teams[index].people.push(person);
+    }
+
+    teams
+}
+
+// Gives each person, best first, to the team with the lowest total
+// score that is not full yet, counting the people pinned to it
+fn fill_weakest_first(
+    pinned: &[Team],
+    people: &[Person],
+    team_sizes: &[usize],
+    sort_by: Option<usize>,
+) -> Vec<Team> {
+    let mut scores: Vec<f32> = pinned
+        .iter()
+        .map(|team| {
+            team.people
+                .iter()
+                .map(|person| person.get_skill_score(sort_by))
+                .sum()
+        })
+        .collect();
+
+    let mut teams = vec![Team::default(); team_sizes.len()];
+
+    for person in people.iter().rev() {
+        let index = (0..teams.len())
+            .filter(|&index| teams[index].people.len() < team_sizes[index])
+            .min_by(|&a, &b| {
+                scores[a]
+                    .partial_cmp(&scores[b])
+                    .expect("Cannot compare team scores")
+            })
+            .expect("Cannot find a team with a free place");
+
+        scores[index] += person.get_skill_score(sort_by);
+        teams[index].people.push(person.clone());
+    }
+
+    teams
+}
+
+// Keeps swapping pairs of people between teams while the cost goes down
+pub fn local_search<F>(teams: &mut [Team], cost: F)
+where
+    F: Fn(&[Team]) -> f32,
+{
+    let mut current_cost = cost(teams);
+    let mut improved = true;
+
+    while improved {
+        improved = false;
+
+        for first in 0..teams.len() {
+            for second in first + 1..teams.len() {
+                for i in 0..teams[first].people.len() {
+                    for j in 0..teams[second].people.len() {
+                        swap_people(teams, (first, i), (second, j));
+
+                        let new_cost = cost(teams);
+
+                        if new_cost < current_cost - COST_EPSILON {
+                            current_cost = new_cost;
+                            improved = true;
+                        } else {
+                            swap_people(teams, (first, i), (second, j));
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+// The first team index must be lower than the second one
+pub fn swap_people(teams: &mut [Team], first: (usize, usize), second: (usize, usize)) {
+    let (left, right) = teams.split_at_mut(second.0);
+    std::mem::swap(
+        &mut left[first.0].people[first.1],
+        &mut right[0].people[second.1],
+    );
+}
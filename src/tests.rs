@@ -3,12 +3,36 @@ extern crate float_cmp;
 use crate::tests::float_cmp::*;
 
 use super::*;
+use crate::builder::*;
+use crate::cli::*;
+use crate::constraints::*;
+use crate::coverage::*;
+use crate::csv_exporter::*;
+use crate::history::*;
+use crate::json::*;
+use crate::preferences::*;
+use crate::strategy::*;
+use crate::text_exporter::*;
+use std::path::Path;
 
 static TEST_FILE_EVEN: &str = "resources/test_even.csv";
 static TEST_FILE_UNEVEN: &str = "resources/test_uneven.csv";
 static TEST_FILE_WRONG: &str = "resources/test.csv";
-#[allow(dead_code)]
+static TEST_FILE_NOT_A_NUMBER: &str = "resources/test_not_a_number.csv";
+static TEST_FILE_WRONG_COLUMNS: &str = "resources/test_wrong_columns.csv";
+static TEST_FILE_EVEN_CONSTRAINTS: &str = "resources/test_even_constraints.csv";
+static TEST_FILE_WEIGHTS: &str = "resources/test_weights.csv";
 static TEST_FILE_SAME_VALUES: &str = "resources/test_same_values.csv";
+static TEST_FILE_EVEN_JSON: &str = "resources/test_even.json";
+static TEST_FILE_EVEN_COMMA: &str = "resources/test_even_comma.csv";
+static TEST_FILE_EVEN_TABS: &str = "resources/test_even_tabs.csv";
+static TEST_FILE_NO_HEADERS: &str = "resources/test_no_headers.csv";
+static TEST_FILE_COLUMNS: &str = "resources/test_columns.csv";
+static TEST_FILE_CATEGORIES: &str = "resources/test_categories.csv";
+static TEST_FILE_EVEN_ROUND: &str = "resources/test_even_round.csv";
+static TEST_FILE_PREFERENCES: &str = "resources/test_preferences.csv";
+static TEST_FILE_PREFERENCES_FILE: &str = "resources/test_preferences_file.csv";
+static TEST_FILE_COVERAGE_SHARED: &str = "resources/test_coverage_shared.csv";
 
 #[allow(dead_code)]
 const SKILL_GAME_DESIGN: usize = 0;
@@ -67,6 +91,45 @@ fn test_load_csv_not_valid_content() {
     assert!(!tb.people_file.is_empty());
 }
 
+#[test]
+fn test_load_csv_wrong_path_error() {
+    let mut tb = TeamBuilder::new();
+    let result = tb.load_file(Path::new(TEST_FILE_WRONG));
+    assert!(matches!(result, Err(TeamBuilderError::Io(_))));
+}
+
+#[test]
+fn test_process_file_not_a_number() {
+    SETUP_TEAMBUILDER_TEST!(TEST_FILE_NOT_A_NUMBER, path, tb);
+    let err = tb.process_file().unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "row 5, column Programming: 'abc' is not a number"
+    );
+}
+
+#[test]
+fn test_process_file_wrong_column_count() {
+    SETUP_TEAMBUILDER_TEST!(TEST_FILE_WRONG_COLUMNS, path, tb);
+    assert!(matches!(
+        tb.process_file(),
+        Err(TeamBuilderError::WrongColumnCount {
+            line: 3,
+            expected: 7,
+            found: 6,
+        })
+    ));
+}
+
+#[test]
+fn test_process_file_missing_header() {
+    let mut tb = TeamBuilder::new();
+    assert!(matches!(
+        tb.process_file(),
+        Err(TeamBuilderError::MissingHeader)
+    ));
+}
+
 #[test]
 fn test_get_people_number_green() {
     SETUP_TEAMBUILDER_TEST_AND_INIT!(TEST_FILE_EVEN, path, tb);
@@ -335,3 +398,1986 @@ fn test_assign_people_to_team_uneven() {
     assert_eq!(first_team, vec!["Bonanni", "Pomettini", "Leotta"]);
     assert_eq!(second_team, vec!["Ricchiuti", "De Dominicis"]);
 }
+
+#[test]
+fn test_optimize_teams_reduces_spread() {
+    SETUP_TEAMBUILDER_TEST_AND_INIT!(TEST_FILE_EVEN, path, tb);
+
+    tb.calculate_teams_skill_level();
+    tb.sort_teams_by_skill_level(None);
+    tb.assign_people_to_team(3);
+
+    let spread_before = tb.get_teams_spread(None);
+    let spread_after = tb.optimize_teams(None);
+
+    assert!(spread_after < spread_before);
+    assert!(approx_eq!(
+        f32,
+        spread_after,
+        tb.get_teams_spread(None),
+        F32Margin::default()
+    ));
+}
+
+#[test]
+fn test_optimize_teams_keeps_team_sizes() {
+    SETUP_TEAMBUILDER_TEST_AND_INIT!(TEST_FILE_UNEVEN, path, tb);
+
+    tb.calculate_teams_skill_level();
+    tb.sort_teams_by_skill_level(Some(SKILL_PROGRAMMING));
+    tb.assign_people_to_team(2);
+    tb.optimize_teams(Some(SKILL_PROGRAMMING));
+
+    let sizes: Vec<usize> = tb.teams.iter().map(|team| team.people.len()).collect();
+    let mut surnames: Vec<String> = tb
+        .teams
+        .iter()
+        .flat_map(|team| team.people.iter().map(|x| x.surname.clone()))
+        .collect();
+    surnames.sort();
+
+    assert_eq!(sizes, vec![3, 2]);
+    assert_eq!(
+        surnames,
+        vec![
+            "Bonanni",
+            "De Dominicis",
+            "Leotta",
+            "Pomettini",
+            "Ricchiuti"
+        ]
+    );
+}
+
+#[test]
+fn test_optimize_teams_all_skills() {
+    SETUP_TEAMBUILDER_TEST_AND_INIT!(TEST_FILE_EVEN, path, tb);
+
+    tb.calculate_teams_skill_level();
+    tb.sort_teams_by_skill_level(None);
+    tb.assign_people_to_team(3);
+
+    let spread_before: f32 = tb
+        .get_skill_balance_report()
+        .iter()
+        .map(|balance| balance.spread)
+        .sum();
+    let spread_after = tb.optimize_teams_all_skills();
+
+    assert!(spread_after < spread_before);
+}
+
+#[test]
+fn test_skill_balance_report() {
+    SETUP_TEAMBUILDER_TEST_AND_INIT!(TEST_FILE_EVEN, path, tb);
+
+    tb.calculate_teams_skill_level();
+    tb.sort_teams_by_skill_level(None);
+    tb.assign_people_to_team(3);
+
+    let report = tb.get_skill_balance_report();
+
+    assert_eq!(report.len(), tb.skills.len());
+    assert_eq!(report[SKILL_PROGRAMMING].skill, "Programming");
+    assert_eq!(report[SKILL_PROGRAMMING].team_averages.len(), 2);
+    assert_eq!(
+        report[SKILL_GAME_DESIGN].to_string(),
+        "Game Design: 2.33, 2.33 (spread 0.00)"
+    );
+}
+
+#[test]
+fn test_get_constraints_path() {
+    assert_eq!(
+        get_constraints_path(Path::new(TEST_FILE_EVEN)),
+        Path::new(TEST_FILE_EVEN_CONSTRAINTS)
+    );
+    assert_eq!(
+        get_preferences_path(Path::new(TEST_FILE_EVEN)),
+        Path::new("resources/test_even_preferences.csv")
+    );
+}
+
+#[test]
+fn test_load_constraints_file() {
+    SETUP_TEAMBUILDER_TEST_AND_INIT!(TEST_FILE_EVEN, path, tb);
+
+    tb.load_constraints_file(Path::new(TEST_FILE_EVEN_CONSTRAINTS))
+        .expect("Cannot load constraints");
+
+    assert_eq!(
+        tb.constraints,
+        vec![
+            Constraint::together(&["Bonanni", "Reclus"]),
+            Constraint::apart(&["Ricchiuti", "De Dominicis"])
+        ]
+    );
+}
+
+#[test]
+fn test_parse_constraints_unknown_kind() {
+    let result = parse_constraints("Constraint;People\nmaybe;Bonanni;Reclus\n");
+    assert_eq!(
+        result.unwrap_err().to_string(),
+        "row 2: unknown constraint 'maybe', expected together or apart"
+    );
+}
+
+#[test]
+fn test_optimize_teams_respects_constraints() {
+    SETUP_TEAMBUILDER_TEST_AND_INIT!(TEST_FILE_EVEN, path, tb);
+
+    tb.calculate_teams_skill_level();
+    tb.sort_teams_by_skill_level(None);
+    tb.assign_people_to_team(3);
+    // Loaded after the draft, which would repair the teams itself
+    tb.load_constraints_file(Path::new(TEST_FILE_EVEN_CONSTRAINTS))
+        .expect("Cannot load constraints");
+
+    assert_eq!(tb.get_constraint_conflicts().len(), 2);
+
+    tb.optimize_teams(None);
+
+    let team_of = get_team_of(&tb.teams);
+
+    assert!(tb.get_constraint_conflicts().is_empty());
+    assert_eq!(team_of["Bonanni"], team_of["Reclus"]);
+    assert_ne!(team_of["Ricchiuti"], team_of["De Dominicis"]);
+}
+
+#[test]
+fn test_strategies_repair_constraints() {
+    SETUP_TEAMBUILDER_TEST_AND_INIT!(TEST_FILE_EVEN, path, tb);
+
+    tb.load_constraints_file(Path::new(TEST_FILE_EVEN_CONSTRAINTS))
+        .expect("Cannot load constraints");
+    tb.calculate_teams_skill_level();
+    tb.sort_teams_by_skill_level(None);
+
+    let strategies: Vec<Box<dyn AssignmentStrategy>> = vec![
+        Box::new(SnakeDraft),
+        Box::new(RoundRobin),
+        Box::new(RandomShuffle { seed: 42 }),
+    ];
+
+    for strategy in &strategies {
+        tb.assign_people_with_strategy(strategy.as_ref(), 3, None)
+            .expect("Cannot assign people");
+
+        let team_of = get_team_of(&tb.teams);
+
+        assert!(tb.get_constraint_conflicts().is_empty());
+        assert_eq!(team_of["Bonanni"], team_of["Reclus"]);
+        assert_ne!(team_of["Ricchiuti"], team_of["De Dominicis"]);
+    }
+}
+
+#[test]
+fn test_constraint_conflicts() {
+    SETUP_TEAMBUILDER_TEST_AND_INIT!(TEST_FILE_EVEN, path, tb);
+
+    tb.constraints = vec![
+        Constraint::together(&["Bonanni", "Reclus"]),
+        Constraint::apart(&["Reclus", "Bonanni"]),
+        Constraint::apart(&["Leotta", "Nobody"]),
+    ];
+
+    let conflicts: Vec<String> = tb
+        .get_constraint_conflicts()
+        .iter()
+        .map(|conflict| conflict.to_string())
+        .collect();
+
+    assert_eq!(
+        conflicts,
+        vec![
+            "Leotta, Nobody must be apart: nobody is called 'Nobody'",
+            "'Bonanni, Reclus must be together' contradicts 'Reclus, Bonanni must be apart'"
+        ]
+    );
+}
+
+#[test]
+fn test_constraint_group_too_large() {
+    SETUP_TEAMBUILDER_TEST_AND_INIT!(TEST_FILE_EVEN, path, tb);
+
+    tb.constraints = vec![Constraint::together(&["Bonanni", "Reclus", "Leotta"])];
+
+    assert!(tb.check_constraint_sizes(&[3, 3]).is_ok());
+    assert_eq!(
+        tb.check_constraint_sizes(&[2, 2, 2])
+            .unwrap_err()
+            .to_string(),
+        "'Bonanni, Reclus, Leotta must be together' cannot be satisfied: \
+         the largest team has 2 people"
+    );
+
+    tb.calculate_teams_skill_level();
+    tb.sort_teams_by_skill_level(None);
+
+    assert!(matches!(
+        tb.assign_people_with_strategy(&SnakeDraft, 2, None),
+        Err(TeamBuilderError::GroupTooLarge { .. })
+    ));
+
+    // Teams made before the constraint was added
+    let constraints = std::mem::take(&mut tb.constraints);
+    tb.assign_people_to_team(2);
+    tb.constraints = constraints;
+
+    assert_eq!(
+        tb.get_constraint_conflicts()[0].to_string(),
+        "Bonanni, Reclus, Leotta must be together: the largest team has 2 people"
+    );
+}
+
+fn get_surnames(team: &Team) -> Vec<String> {
+    team.people.iter().map(|x| x.surname.clone()).collect()
+}
+
+#[test]
+fn test_assign_people_round_robin() {
+    SETUP_TEAMBUILDER_TEST_AND_INIT!(TEST_FILE_EVEN, path, tb);
+
+    tb.calculate_teams_skill_level();
+    tb.sort_teams_by_skill_level(None);
+    tb.assign_people_with_strategy(&RoundRobin, 3, None)
+        .expect("Cannot assign people");
+
+    assert_eq!(
+        get_surnames(&tb.teams[0]),
+        vec!["Bonanni", "De Dominicis", "Leotta"]
+    );
+    assert_eq!(
+        get_surnames(&tb.teams[1]),
+        vec!["Ricchiuti", "Pomettini", "Reclus"]
+    );
+}
+
+#[test]
+fn test_assign_people_random_shuffle_same_seed() {
+    SETUP_TEAMBUILDER_TEST_AND_INIT!(TEST_FILE_EVEN, path, tb);
+
+    tb.calculate_teams_skill_level();
+    tb.sort_teams_by_skill_level(None);
+
+    tb.assign_people_with_strategy(&RandomShuffle { seed: 42 }, 2, None)
+        .expect("Cannot assign people");
+    let first_run: Vec<Vec<String>> = tb.teams.iter().map(get_surnames).collect();
+
+    tb.assign_people_with_strategy(&RandomShuffle { seed: 42 }, 2, None)
+        .expect("Cannot assign people");
+    let second_run: Vec<Vec<String>> = tb.teams.iter().map(get_surnames).collect();
+
+    assert_eq!(first_run, second_run);
+    assert_eq!(first_run.len(), 3);
+    assert_eq!(first_run.iter().map(|team| team.len()).sum::<usize>(), 6);
+}
+
+#[test]
+fn test_assign_people_optimizing() {
+    SETUP_TEAMBUILDER_TEST_AND_INIT!(TEST_FILE_EVEN, path, tb);
+
+    tb.calculate_teams_skill_level();
+    tb.sort_teams_by_skill_level(None);
+
+    tb.assign_people_with_strategy(&SnakeDraft, 3, None)
+        .expect("Cannot assign people");
+    let snake_spread = tb.get_teams_spread(None);
+
+    tb.assign_people_with_strategy(&Optimizing { all_skills: false }, 3, None)
+        .expect("Cannot assign people");
+
+    assert!(tb.get_teams_spread(None) < snake_spread);
+}
+
+#[test]
+fn test_get_strategies() {
+    let names: Vec<String> = get_strategies(0)
+        .iter()
+        .map(|strategy| strategy.name().to_string())
+        .collect();
+
+    assert_eq!(
+        names,
+        vec![
+            "Optimized",
+            "Optimized (all skills)",
+            "Snake draft",
+            "Round robin",
+            "Random shuffle"
+        ]
+    );
+}
+
+#[test]
+fn test_check_team_sizes_uneven() {
+    SETUP_TEAMBUILDER_TEST_AND_INIT!(TEST_FILE_UNEVEN, path, tb);
+    assert_eq!(tb.check_team_sizes(3), Some(vec![2, 2, 1]));
+}
+
+#[test]
+fn test_check_team_sizes_too_many_teams() {
+    SETUP_TEAMBUILDER_TEST_AND_INIT!(TEST_FILE_UNEVEN, path, tb);
+    assert_eq!(tb.check_team_sizes(0), None);
+    assert_eq!(tb.check_team_sizes(6), None);
+}
+
+#[test]
+fn test_assign_people_no_feasible_split() {
+    SETUP_TEAMBUILDER_TEST_AND_INIT!(TEST_FILE_EVEN, path, tb);
+
+    tb.calculate_teams_skill_level();
+    tb.sort_teams_by_skill_level(None);
+
+    assert_eq!(
+        tb.assign_people_with_strategy(&SnakeDraft, 6, None)
+            .unwrap_err()
+            .to_string(),
+        "cannot split 6 people into teams of 6 to 6 people"
+    );
+    assert_eq!(
+        tb.assign_people_to_number_of_teams(&SnakeDraft, 7, None)
+            .unwrap_err()
+            .to_string(),
+        "cannot split 6 people into teams of 0 to 1 people"
+    );
+    assert!(tb.teams.is_empty());
+}
+
+#[test]
+fn test_assign_people_to_number_of_teams() {
+    SETUP_TEAMBUILDER_TEST_AND_INIT!(TEST_FILE_EVEN, path, tb);
+
+    tb.calculate_teams_skill_level();
+    tb.sort_teams_by_skill_level(None);
+    tb.assign_people_to_number_of_teams(&Optimizing { all_skills: false }, 4, None)
+        .expect("Cannot assign people");
+
+    let mut sizes: Vec<usize> = tb.teams.iter().map(|team| team.people.len()).collect();
+    sizes.sort();
+
+    assert_eq!(sizes, vec![1, 1, 2, 2]);
+}
+
+#[test]
+fn test_get_team_layout_spread() {
+    SETUP_TEAMBUILDER_TEST_AND_INIT!(TEST_FILE_EVEN, path, tb);
+    tb.remainder_policy = RemainderPolicy::Spread;
+    assert_eq!(tb.get_team_layout(4), Some((vec![6], 0)));
+}
+
+#[test]
+fn test_get_team_layout_extra_team() {
+    SETUP_TEAMBUILDER_TEST_AND_INIT!(TEST_FILE_EVEN, path, tb);
+    tb.remainder_policy = RemainderPolicy::ExtraTeam;
+    assert_eq!(tb.get_team_layout(4), Some((vec![4, 2], 0)));
+}
+
+#[test]
+fn test_get_team_layout_reserve() {
+    SETUP_TEAMBUILDER_TEST_AND_INIT!(TEST_FILE_EVEN, path, tb);
+    tb.remainder_policy = RemainderPolicy::Reserve;
+    assert_eq!(tb.get_team_layout(4), Some((vec![4], 2)));
+}
+
+#[test]
+fn test_assign_people_to_team_reserve() {
+    SETUP_TEAMBUILDER_TEST_AND_INIT!(TEST_FILE_UNEVEN, path, tb);
+
+    tb.remainder_policy = RemainderPolicy::Reserve;
+    tb.calculate_teams_skill_level();
+    tb.sort_teams_by_skill_level(None);
+    tb.assign_people_to_team(2);
+
+    assert_eq!(get_surnames(&tb.teams[0]), vec!["Bonanni", "Pomettini"]);
+    assert_eq!(
+        get_surnames(&tb.teams[1]),
+        vec!["Ricchiuti", "De Dominicis"]
+    );
+    assert_eq!(tb.reserve.len(), 1);
+    assert_eq!(tb.reserve[0].surname, "Leotta");
+
+    let html = html_exporter::generate_html(&tb).unwrap();
+    assert!(html.contains("Leftover people are kept in reserve"));
+    assert!(html.contains("<h2>Reserve</h2>"));
+    assert!(html.contains("<tr><td>Leotta</td><td>2</td>"));
+}
+
+#[test]
+fn test_assign_people_to_team_extra_team() {
+    SETUP_TEAMBUILDER_TEST_AND_INIT!(TEST_FILE_UNEVEN, path, tb);
+
+    tb.remainder_policy = RemainderPolicy::ExtraTeam;
+    tb.calculate_teams_skill_level();
+    tb.sort_teams_by_skill_level(None);
+    tb.assign_people_to_team(2);
+
+    let sizes: Vec<usize> = tb.teams.iter().map(|team| team.people.len()).collect();
+
+    assert_eq!(sizes, vec![2, 2, 1]);
+    assert!(tb.reserve.is_empty());
+}
+
+#[test]
+fn test_load_csv_with_weights() {
+    SETUP_TEAMBUILDER_TEST_AND_INIT!(TEST_FILE_WEIGHTS, path, tb);
+    assert_eq!(tb.people.len(), 6);
+    assert_eq!(tb.skill_weights, vec![1.0, 1.0, 2.0, 1.0, 1.0, 1.0]);
+}
+
+#[test]
+fn test_load_csv_without_weights() {
+    SETUP_TEAMBUILDER_TEST_AND_INIT!(TEST_FILE_EVEN, path, tb);
+    assert_eq!(tb.skill_weights, vec![1.0; 6]);
+}
+
+#[test]
+fn test_load_csv_invalid_weight() {
+    let mut tb = TeamBuilder::new();
+    tb.people_file = "Surname;Programming;Graphics\nWeight;2;-1\nPomettini;3;2\n".to_string();
+    assert_eq!(
+        tb.process_file().unwrap_err().to_string(),
+        "row 2, column Graphics: '-1' is not a valid weight"
+    );
+
+    for weight in &["inf", "NaN"] {
+        tb.people_file = format!("Surname;Programming\nWeight;{}\nPomettini;3\n", weight);
+        assert!(matches!(
+            tb.process_file(),
+            Err(TeamBuilderError::InvalidWeight { .. })
+        ));
+    }
+}
+
+#[test]
+fn test_calculate_weighted_skill_level() {
+    SETUP_TEAMBUILDER_TEST_AND_INIT!(TEST_FILE_WEIGHTS, path, tb);
+
+    tb.calculate_teams_skill_level();
+
+    let person = tb
+        .people
+        .iter()
+        .find(|&x| x.surname == "Pomettini")
+        .unwrap();
+
+    assert!(approx_eq!(
+        f32,
+        person.average_skill_level,
+        2.0,
+        F32Margin::default()
+    ));
+}
+
+#[test]
+fn test_default_matches_new() {
+    let tb = TeamBuilder::default();
+
+    assert!(approx_eq!(
+        f32,
+        tb.preference_weight,
+        1.0,
+        F32Margin::default()
+    ));
+    assert!(approx_eq!(f32, tb.repeat_weight, 1.0, F32Margin::default()));
+    assert_eq!(tb.remainder_policy, RemainderPolicy::Spread);
+}
+
+#[test]
+fn test_set_skill_weights_changes_sorting() {
+    SETUP_TEAMBUILDER_TEST_AND_INIT!(TEST_FILE_EVEN, path, tb);
+
+    tb.set_skill_weights(vec![0.0, 0.0, 1.0, 0.0, 0.0, 0.0])
+        .expect("Cannot set weights");
+    tb.calculate_teams_skill_level();
+    tb.sort_teams_by_skill_level(None);
+
+    assert_eq!(tb.people.last().unwrap().surname, "Pomettini");
+}
+
+#[test]
+fn test_set_skill_weights_invalid() {
+    SETUP_TEAMBUILDER_TEST_AND_INIT!(TEST_FILE_EVEN, path, tb);
+
+    assert_eq!(
+        tb.set_skill_weights(vec![1.0, 1.0])
+            .unwrap_err()
+            .to_string(),
+        "row 0: expected 6 weights, found 2"
+    );
+    assert!(tb
+        .set_skill_weights(vec![1.0, 1.0, f32::NAN, 1.0, 1.0, 1.0])
+        .is_err());
+    assert!(tb
+        .set_skill_weights(vec![1.0, 1.0, f32::INFINITY, 1.0, 1.0, 1.0])
+        .is_err());
+    assert!(tb
+        .set_skill_weights(vec![1.0, 1.0, -1.0, 1.0, 1.0, 1.0])
+        .is_err());
+    assert_eq!(tb.skill_weights, vec![1.0; 6]);
+}
+
+#[test]
+fn test_check_team_counts() {
+    SETUP_TEAMBUILDER_TEST_AND_INIT!(TEST_FILE_EVEN, path, tb);
+    assert_eq!(tb.check_team_counts(2, 3).unwrap(), vec![2, 3]);
+    assert_eq!(tb.check_team_counts(6, 6).unwrap(), vec![1]);
+}
+
+#[test]
+fn test_check_team_counts_not_feasible() {
+    SETUP_TEAMBUILDER_TEST_AND_INIT!(TEST_FILE_EVEN, path, tb);
+    assert_eq!(
+        tb.check_team_counts(4, 5).unwrap_err().to_string(),
+        "cannot split 6 people into teams of 4 to 5 people"
+    );
+    assert!(tb.check_team_counts(3, 2).is_err());
+}
+
+#[test]
+fn test_choose_number_of_teams() {
+    SETUP_TEAMBUILDER_TEST_AND_INIT!(TEST_FILE_EVEN, path, tb);
+    assert_eq!(tb.choose_number_of_teams(2, 3).unwrap(), 3);
+    assert_eq!(tb.choose_number_of_teams(2, 6).unwrap(), 2);
+}
+
+#[test]
+fn test_assign_people_in_size_range() {
+    SETUP_TEAMBUILDER_TEST_AND_INIT!(TEST_FILE_UNEVEN, path, tb);
+
+    tb.calculate_teams_skill_level();
+    tb.sort_teams_by_skill_level(None);
+    tb.assign_people_in_size_range(&Optimizing { all_skills: false }, 2, 3, None)
+        .expect("Cannot assign people");
+
+    let mut sizes: Vec<usize> = tb.teams.iter().map(|team| team.people.len()).collect();
+    sizes.sort();
+
+    assert_eq!(sizes, vec![2, 3]);
+}
+
+fn get_people_surnames(tb: &TeamBuilder) -> Vec<String> {
+    tb.people.iter().map(|x| x.surname.clone()).collect()
+}
+
+#[test]
+fn test_sort_without_seed_keeps_file_order() {
+    SETUP_TEAMBUILDER_TEST_AND_INIT!(TEST_FILE_SAME_VALUES, path, tb);
+
+    let file_order = get_people_surnames(&tb);
+
+    tb.calculate_teams_skill_level();
+    tb.sort_teams_by_skill_level(None);
+
+    assert_eq!(get_people_surnames(&tb), file_order);
+}
+
+#[test]
+fn test_sort_with_seed_mixes_ties() {
+    SETUP_TEAMBUILDER_TEST_AND_INIT!(TEST_FILE_SAME_VALUES, path, tb);
+
+    tb.calculate_teams_skill_level();
+
+    tb.seed = Some(1);
+    tb.sort_teams_by_skill_level(None);
+    let first_seed = get_people_surnames(&tb);
+
+    tb.seed = Some(2);
+    tb.sort_teams_by_skill_level(None);
+    let second_seed = get_people_surnames(&tb);
+
+    assert_ne!(first_seed, second_seed);
+}
+
+#[test]
+fn test_same_seed_gives_same_teams() {
+    SETUP_TEAMBUILDER_TEST_AND_INIT!(TEST_FILE_SAME_VALUES, path, tb);
+
+    tb.seed = Some(1234);
+    tb.calculate_teams_skill_level();
+
+    tb.sort_teams_by_skill_level(None);
+    tb.assign_people_to_team(2);
+    let first_run: Vec<Vec<String>> = tb.teams.iter().map(get_surnames).collect();
+
+    tb.sort_teams_by_skill_level(None);
+    tb.assign_people_to_team(2);
+    let second_run: Vec<Vec<String>> = tb.teams.iter().map(get_surnames).collect();
+
+    assert_eq!(first_run, second_run);
+
+    let html = html_exporter::generate_html(&tb).unwrap();
+    assert!(html.contains("Seed: 1234"));
+}
+
+fn get_args(args: &[&str]) -> Vec<String> {
+    args.iter().map(|arg| arg.to_string()).collect()
+}
+
+#[test]
+fn test_cli_parse_generate_args() {
+    let options = parse_generate_args(&get_args(&[
+        "--input",
+        TEST_FILE_EVEN,
+        "--team-size",
+        "3",
+        "--sort-by",
+        "Programming",
+        "--format",
+        "html",
+        "--output",
+        "teams.html",
+    ]))
+    .expect("Cannot parse arguments");
+
+    assert_eq!(options.input, Path::new(TEST_FILE_EVEN));
+    assert_eq!(options.layout, TeamLayout::TeamSize(3));
+    assert_eq!(options.sort_by, Some("Programming".to_string()));
+    assert_eq!(options.strategy, "optimized");
+    assert_eq!(options.format, Format::Html);
+    assert_eq!(options.output, Some(Path::new("teams.html").to_path_buf()));
+}
+
+#[test]
+fn test_cli_parse_generate_args_missing_layout() {
+    let err = parse_generate_args(&get_args(&["--input", TEST_FILE_EVEN])).unwrap_err();
+    assert_eq!(err.get_exit_code(), EXIT_USAGE);
+}
+
+#[test]
+fn test_cli_strategy_keys() {
+    let keys: Vec<String> = get_strategies(0)
+        .iter()
+        .map(|strategy| get_strategy_key(strategy.as_ref()))
+        .collect();
+
+    assert_eq!(
+        keys,
+        vec![
+            "optimized",
+            "optimized-all-skills",
+            "snake-draft",
+            "round-robin",
+            "random-shuffle"
+        ]
+    );
+}
+
+#[test]
+fn test_cli_generate() {
+    let options = parse_generate_args(&get_args(&[
+        "--input",
+        TEST_FILE_EVEN,
+        "--teams",
+        "3",
+        "--sort-by",
+        "Programming",
+        "--seed",
+        "7",
+        "--team-names",
+        "Red, Blue",
+    ]))
+    .expect("Cannot parse arguments");
+
+    let tb = generate(&options).expect("Cannot generate teams");
+
+    assert_eq!(tb.teams.len(), 3);
+    assert_eq!(tb.seed, Some(7));
+    assert_eq!(tb.get_team_title(1), "Blue");
+    assert_eq!(tb.get_team_title(2), "Team Charlie");
+
+    let html = export(&tb, Format::Html);
+    assert!(html.contains("Seed: 7"));
+    assert!(html.contains("<h2>Red</h2>"));
+}
+
+#[test]
+fn test_cli_exit_codes() {
+    let generate_args = |input: &str| get_args(&["generate", "--input", input, "--team-size", "2"]);
+
+    assert_eq!(run(&generate_args(TEST_FILE_WRONG)), EXIT_IO);
+    assert_eq!(
+        run(&generate_args(TEST_FILE_NOT_A_NUMBER)),
+        EXIT_NOT_A_NUMBER
+    );
+    assert_eq!(
+        run(&generate_args(TEST_FILE_WRONG_COLUMNS)),
+        EXIT_WRONG_COLUMN_COUNT
+    );
+    assert_eq!(run(&get_args(&["unknown"])), EXIT_USAGE);
+    assert_eq!(
+        run(&get_args(&[
+            "generate",
+            "--input",
+            TEST_FILE_EVEN,
+            "--team-size",
+            "9"
+        ])),
+        EXIT_NO_FEASIBLE_SPLIT
+    );
+}
+
+#[test]
+fn test_get_team_name() {
+    assert_eq!(get_team_name(0), "Alfa");
+    assert_eq!(get_team_name(9), "Juliett");
+    assert_eq!(get_team_name(10), "11");
+}
+
+#[test]
+fn test_generate_spreadsheet() {
+    SETUP_TEAMBUILDER_TEST_AND_INIT!(TEST_FILE_EVEN, path, tb);
+
+    tb.calculate_teams_skill_level();
+    tb.sort_teams_by_skill_level(None);
+    tb.assign_people_to_team(2);
+
+    let output = std::env::temp_dir().join("team_builder_test_generate_spreadsheet.xlsx");
+
+    generate_spreadsheet(&tb, &output).expect("Cannot generate spreadsheet");
+
+    // An xlsx file is a zip archive
+    let contents = std::fs::read(&output).expect("Cannot read spreadsheet");
+    assert!(contents.starts_with(b"PK"));
+
+    std::fs::remove_file(&output).expect("Cannot remove spreadsheet");
+}
+
+#[test]
+fn test_generate_spreadsheet_missing_directory() {
+    SETUP_TEAMBUILDER_TEST_AND_INIT!(TEST_FILE_EVEN, path, tb);
+
+    tb.calculate_teams_skill_level();
+    tb.sort_teams_by_skill_level(None);
+    tb.assign_people_to_team(2);
+
+    let result = generate_spreadsheet(&tb, Path::new("missing_directory/teams.xlsx"));
+
+    assert!(matches!(result, Err(TeamBuilderError::Io(_))));
+}
+
+#[test]
+fn test_escape_html() {
+    assert_eq!(
+        html_exporter::escape_html("<b>Tom & \"Jerry\"</b>"),
+        "&lt;b&gt;Tom &amp; &quot;Jerry&quot;&lt;/b&gt;"
+    );
+    assert_eq!(html_exporter::escape_html("O'Brien"), "O&#39;Brien");
+}
+
+#[test]
+fn test_generate_html_report() {
+    SETUP_TEAMBUILDER_TEST_AND_INIT!(TEST_FILE_EVEN, path, tb);
+
+    tb.people[0].surname = "<i>De Dominicis</i>".to_string();
+    tb.team_names = vec![String::new(), "Red & Blue".to_string()];
+    tb.calculate_teams_skill_level();
+    tb.sort_teams_by_skill_level(None);
+    tb.assign_people_to_team(3);
+
+    let html = html_exporter::generate_html(&tb).unwrap();
+
+    assert!(html.contains("<h2>Team Alfa</h2>"));
+    assert!(html.contains("<h2>Red &amp; Blue</h2>"));
+    assert!(html.contains("<th>Game Design</th>"));
+    assert!(html.contains("&lt;i&gt;De Dominicis&lt;/i&gt;"));
+    assert!(!html.contains("<i>"));
+
+    // Roster summary
+    assert!(html.contains("<tr><th>People</th><td>6</td></tr>"));
+    assert!(html.contains("<tr><th>Teams</th><td>2</td></tr>"));
+    assert!(html.contains("<tr><th>Game Design</th><td>2.33</td></tr>"));
+
+    // Both teams average 2.33 in Game Design
+    assert_eq!(html.matches("<tr><th>Average</th><td>2.33</td>").count(), 2);
+}
+
+#[test]
+fn test_process_json() {
+    SETUP_TEAMBUILDER_TEST_AND_INIT!(TEST_FILE_EVEN, path, csv_tb);
+    SETUP_TEAMBUILDER_TEST!(TEST_FILE_EVEN_JSON, json_path, tb);
+
+    assert!(is_json_path(json_path));
+    assert!(!is_json_path(path));
+
+    tb.process_json().expect("Cannot process JSON");
+
+    assert_eq!(tb.skills, csv_tb.skills);
+    assert_eq!(tb.skill_weights, csv_tb.skill_weights);
+    assert_eq!(tb.people, csv_tb.people);
+}
+
+#[test]
+fn test_parse_roster_errors() {
+    assert!(matches!(
+        parse_roster("{ \"skills\": [\"Programming\"] "),
+        Err(TeamBuilderError::Malformed { line: 1, .. })
+    ));
+    assert!(matches!(
+        parse_roster("{ \"skills\": [], \"people\": [] }"),
+        Err(TeamBuilderError::MissingHeader)
+    ));
+    assert!(matches!(
+        parse_roster("{ \"skills\": [\"Programming\"], \"weights\": [-1], \"people\": [] }"),
+        Err(TeamBuilderError::InvalidWeight { .. })
+    ));
+
+    let result = parse_roster(
+        "{ \"skills\": [\"Programming\", \"Graphics\"], \"people\": [
+            { \"surname\": \"Tizio\", \"skill_levels\": [1, 2] },
+            { \"surname\": \"Caio\", \"skill_levels\": [1] }
+        ] }",
+    );
+
+    assert_eq!(
+        result.unwrap_err().to_string(),
+        "row 2: 'Caio' has 1 skill levels, expected 2"
+    );
+}
+
+#[test]
+fn test_generate_json() {
+    SETUP_TEAMBUILDER_TEST_AND_INIT!(TEST_FILE_EVEN, path, tb);
+
+    tb.seed = Some(42);
+    tb.calculate_teams_skill_level();
+    tb.sort_teams_by_skill_level(None);
+    tb.assign_people_to_team(3);
+
+    let json = generate_json(&tb).unwrap();
+    let snapshot: TeamBuilderSnapshot = serde_json::from_str(&json).expect("Cannot read JSON");
+
+    assert_eq!(snapshot, TeamBuilderSnapshot::from(&tb));
+    assert_eq!(snapshot.seed, Some(42));
+    assert_eq!(snapshot.remainder_policy, RemainderPolicy::Spread);
+    assert_eq!(snapshot.teams.len(), 2);
+    assert_eq!(snapshot.teams[0].name, "Team Alfa");
+    assert_eq!(snapshot.teams[0].people, tb.teams[0].people);
+    assert_eq!(snapshot.teams[0].skill_averages.len(), tb.skills.len());
+    assert!(json.contains("\"remainder_policy\": \"spread\""));
+}
+
+#[test]
+fn test_cli_generate_json() {
+    let options = parse_generate_args(&get_args(&[
+        "--input",
+        TEST_FILE_EVEN_JSON,
+        "--team-size",
+        "3",
+        "--format",
+        "json",
+    ]))
+    .expect("Cannot parse arguments");
+
+    let tb = generate(&options).expect("Cannot generate teams");
+    let snapshot: TeamBuilderSnapshot =
+        serde_json::from_str(&export(&tb, Format::Json)).expect("Cannot read JSON");
+
+    assert_eq!(snapshot.teams.len(), 2);
+    assert_eq!(snapshot.skills.len(), 6);
+}
+
+#[test]
+fn test_generate_csv() {
+    SETUP_TEAMBUILDER_TEST_AND_INIT!(TEST_FILE_UNEVEN, path, tb);
+
+    tb.remainder_policy = RemainderPolicy::Reserve;
+    tb.calculate_teams_skill_level();
+    tb.sort_teams_by_skill_level(None);
+    tb.assign_people_to_team(2);
+
+    let csv = generate_csv(&tb, b';').unwrap();
+    let lines: Vec<&str> = csv.lines().collect();
+
+    assert_eq!(
+        lines[0],
+        "Team;Name;Game Design;Level Design;Programming;Narrative;Graphics;Teamwork;Average"
+    );
+    assert_eq!(lines.len(), 1 + tb.people.len());
+    assert!(lines[1].starts_with("Team Alfa;Bonanni;"));
+    assert_eq!(lines[5], "Reserve;Leotta;2;1;1;3;1;2;1.67");
+
+    let csv = generate_csv(&tb, b',').unwrap();
+    assert!(csv.contains("Reserve,Leotta,2,1,1,3,1,2,1.67"));
+}
+
+#[test]
+fn test_cli_generate_csv() {
+    let args = |extra: &[&str]| {
+        let mut args = vec!["--input", TEST_FILE_EVEN, "--team-size", "3"];
+        args.extend_from_slice(extra);
+        get_args(&args)
+    };
+
+    let options = parse_generate_args(&args(&["--format", "csv", "--delimiter", ","]))
+        .expect("Cannot parse arguments");
+    assert_eq!(options.format, Format::Csv(b','));
+
+    let tb = generate(&options).expect("Cannot generate teams");
+    assert!(export(&tb, Format::Csv(b',')).starts_with("Team,Name,Game Design"));
+
+    assert!(matches!(
+        parse_generate_args(&args(&["--delimiter", ","])),
+        Err(CliError::Usage(_))
+    ));
+}
+
+#[test]
+fn test_generate_markdown() {
+    SETUP_TEAMBUILDER_TEST_AND_INIT!(TEST_FILE_EVEN, path, tb);
+
+    tb.people[0].surname = "De_Dominicis".to_string();
+    tb.calculate_teams_skill_level();
+    tb.sort_teams_by_skill_level(None);
+    tb.assign_people_to_team(3);
+
+    let markdown = generate_markdown(&tb);
+
+    assert!(markdown.starts_with("## Team Alfa\n\n| Name | Average |\n| --- | ---: |\n"));
+    assert!(markdown.contains("\n## Team Bravo\n"));
+    assert!(markdown.contains("| De\\_Dominicis | 2.00 |\n"));
+    assert_eq!(markdown.matches("| **Team average** |").count(), 2);
+    assert!(markdown.ends_with("\nLeftover people join the teams, one per team\n"));
+}
+
+#[test]
+fn test_generate_markdown_reserve() {
+    SETUP_TEAMBUILDER_TEST_AND_INIT!(TEST_FILE_EVEN, path, tb);
+
+    tb.team_names = vec!["Red".to_string()];
+    tb.remainder_policy = RemainderPolicy::Reserve;
+    tb.calculate_teams_skill_level();
+    tb.sort_teams_by_skill_level(None);
+    tb.assign_people_to_team(4);
+
+    tb.seed = Some(42);
+
+    let markdown = generate_markdown(&tb);
+
+    assert!(markdown.starts_with("## Red\n"));
+    assert!(markdown.contains("\n## Reserve\n\n| Name | Average |\n"));
+    assert!(markdown.ends_with("\nLeftover people are kept in reserve\n\nSeed: 42\n"));
+}
+
+#[test]
+fn test_generate_text() {
+    SETUP_TEAMBUILDER_TEST_AND_INIT!(TEST_FILE_EVEN, path, tb);
+
+    tb.calculate_teams_skill_level();
+    tb.sort_teams_by_skill_level(None);
+    tb.assign_people_to_team(3);
+
+    let text = generate_text(&tb);
+    let lines: Vec<&str> = text.lines().collect();
+
+    assert!(lines[0].starts_with("Team Alfa (average "));
+    assert_eq!(lines.len(), 2 * 5 + 1);
+    assert_eq!(lines[10], "Leftover people join the teams, one per team");
+
+    // The averages line up, "De Dominicis" is the longest name
+    for line in lines.iter().filter(|line| line.starts_with("  ")) {
+        assert_eq!(line.chars().count(), 2 + "De Dominicis".len() + 2 + 5);
+    }
+
+    assert!(text.contains("  Reclus         1.33\n"));
+}
+
+#[test]
+fn test_generate_text_reserve() {
+    SETUP_TEAMBUILDER_TEST_AND_INIT!(TEST_FILE_EVEN, path, tb);
+
+    tb.team_names = vec!["Red".to_string()];
+    tb.remainder_policy = RemainderPolicy::Reserve;
+    tb.calculate_teams_skill_level();
+    tb.sort_teams_by_skill_level(None);
+    tb.assign_people_to_team(4);
+
+    tb.seed = Some(42);
+
+    let text = generate_text(&tb);
+
+    assert!(text.starts_with("Red (average "));
+    assert!(text.contains("\nReserve\n  Reclus         1.33\n"));
+    assert!(text.ends_with("\nLeftover people are kept in reserve\nSeed: 42\n"));
+}
+
+#[test]
+fn test_sniff_delimiter() {
+    assert_eq!(sniff_delimiter("Name;A;B\nTizio;1;2"), b';');
+    assert_eq!(sniff_delimiter("Name,A,B\nTizio,1,2"), b',');
+    assert_eq!(sniff_delimiter("Name\tA\tB\nTizio\t1\t2"), b'\t');
+    assert_eq!(sniff_delimiter("Name|A|B\nTizio|1|2"), b'|');
+
+    // Commas inside the names are not counted on every line
+    assert_eq!(
+        sniff_delimiter("Name;A;B\nRossi, Mario;1;2\nTizio;1;2"),
+        b';'
+    );
+
+    // Semicolon when there is nothing to go by
+    assert_eq!(sniff_delimiter("Name"), b';');
+}
+
+#[test]
+fn test_process_file_dialects() {
+    SETUP_TEAMBUILDER_TEST_AND_INIT!(TEST_FILE_EVEN, path, expected);
+
+    for file in &[TEST_FILE_EVEN_COMMA, TEST_FILE_EVEN_TABS] {
+        SETUP_TEAMBUILDER_TEST_AND_INIT!(file, path, tb);
+
+        assert_eq!(tb.skills, expected.skills);
+        assert_eq!(tb.people, expected.people);
+    }
+}
+
+#[test]
+fn test_process_file_explicit_dialect() {
+    SETUP_TEAMBUILDER_TEST!(TEST_FILE_EVEN_COMMA, path, tb);
+
+    tb.csv_dialect.delimiter = Some(b';');
+    assert!(matches!(
+        tb.process_file(),
+        Err(TeamBuilderError::MissingHeader)
+    ));
+
+    tb.csv_dialect.delimiter = Some(b',');
+    tb.process_file().expect("Cannot process file");
+    assert_eq!(tb.people[0].surname, "De Dominicis");
+
+    // With another quote character the quotes are part of the name
+    tb.csv_dialect.quote = b'\'';
+    tb.process_file().expect("Cannot process file");
+    assert_eq!(tb.people[0].surname, "\"De Dominicis\"");
+}
+
+#[test]
+fn test_process_file_no_headers() {
+    SETUP_TEAMBUILDER_TEST!(TEST_FILE_NO_HEADERS, path, tb);
+
+    tb.csv_dialect.has_headers = false;
+    tb.process_file().expect("Cannot process file");
+
+    assert_eq!(tb.skills.len(), 6);
+    assert_eq!(tb.skills[0], "Skill 1");
+    assert_eq!(tb.skills[5], "Skill 6");
+    assert_eq!(tb.people.len(), 6);
+    assert_eq!(tb.people[0].surname, "De Dominicis");
+}
+
+#[test]
+fn test_cli_csv_dialect() {
+    let options = parse_generate_args(&get_args(&[
+        "--input",
+        TEST_FILE_NO_HEADERS,
+        "--team-size",
+        "3",
+        "--input-delimiter",
+        ";",
+        "--quote",
+        "'",
+        "--headers",
+        "no",
+    ]))
+    .expect("Cannot parse arguments");
+
+    assert_eq!(
+        options.csv_dialect,
+        CsvDialect {
+            delimiter: Some(b';'),
+            quote: b'\'',
+            has_headers: false,
+        }
+    );
+
+    let tb = generate(&options).expect("Cannot generate teams");
+    assert_eq!(tb.people.len(), 6);
+    assert_eq!(tb.skills[0], "Skill 1");
+}
+
+fn get_test_columns_mapping() -> ColumnMapping {
+    ColumnMapping {
+        roles: vec![
+            ColumnRole::Name,
+            ColumnRole::Name,
+            ColumnRole::Metadata,
+            ColumnRole::Ignored,
+            ColumnRole::Skill,
+            ColumnRole::Skill,
+        ],
+    }
+}
+
+#[test]
+fn test_process_file_extra_columns_without_mapping() {
+    SETUP_TEAMBUILDER_TEST!(TEST_FILE_COLUMNS, path, tb);
+
+    assert!(matches!(
+        tb.process_file(),
+        Err(TeamBuilderError::NotANumber { .. })
+    ));
+}
+
+#[test]
+fn test_guess_column_mapping() {
+    SETUP_TEAMBUILDER_TEST!(TEST_FILE_COLUMNS, path, tb);
+
+    assert_eq!(
+        tb.get_columns().unwrap(),
+        vec![
+            "First name",
+            "Last name",
+            "Email",
+            "Class",
+            "Programming",
+            "Graphics"
+        ]
+    );
+    assert_eq!(
+        tb.guess_column_mapping().unwrap().roles,
+        vec![
+            ColumnRole::Name,
+            ColumnRole::Metadata,
+            ColumnRole::Metadata,
+            ColumnRole::Metadata,
+            ColumnRole::Skill,
+            ColumnRole::Skill,
+        ]
+    );
+}
+
+#[test]
+fn test_process_file_column_mapping() {
+    SETUP_TEAMBUILDER_TEST!(TEST_FILE_COLUMNS, path, tb);
+
+    tb.column_mapping = Some(get_test_columns_mapping());
+    tb.process_file().expect("Cannot process file");
+
+    assert_eq!(tb.skills, vec!["Programming", "Graphics"]);
+    assert_eq!(tb.metadata, vec!["Email"]);
+    assert_eq!(tb.people.len(), 4);
+    assert_eq!(tb.people[0].surname, "Mario Rossi");
+    assert_eq!(tb.people[0].skill_levels, vec![3, 1]);
+    assert_eq!(tb.people[0].metadata, vec!["mario@example.com"]);
+
+    // The mapping must have a role for each column
+    tb.column_mapping = Some(ColumnMapping::new(3));
+    assert!(matches!(
+        tb.process_file(),
+        Err(TeamBuilderError::WrongColumnCount { .. })
+    ));
+}
+
+#[test]
+fn test_exports_metadata() {
+    SETUP_TEAMBUILDER_TEST!(TEST_FILE_COLUMNS, path, tb);
+
+    tb.column_mapping = Some(get_test_columns_mapping());
+    tb.process_file().expect("Cannot process file");
+    tb.calculate_teams_skill_level();
+    tb.sort_teams_by_skill_level(None);
+    tb.assign_people_to_team(2);
+
+    let csv = generate_csv(&tb, b';').unwrap();
+    assert!(csv.starts_with("Team;Name;Email;Programming;Graphics;Average\n"));
+    assert!(csv.contains(";Mario Rossi;mario@example.com;3;1;2.00\n"));
+
+    let html = html_exporter::generate_html(&tb).unwrap();
+    assert!(html.contains("<th>Email</th>"));
+    assert!(html.contains("<td>Mario Rossi</td><td>mario@example.com</td>"));
+
+    let snapshot = TeamBuilderSnapshot::from(&tb);
+    assert_eq!(snapshot.metadata, vec!["Email"]);
+}
+
+#[test]
+fn test_cli_column_names() {
+    let args = |extra: &[&str]| {
+        let mut args = vec!["--input", TEST_FILE_COLUMNS, "--team-size", "2"];
+        args.extend_from_slice(extra);
+        get_args(&args)
+    };
+
+    let options = parse_generate_args(&args(&[
+        "--name-columns",
+        "First name, Last name",
+        "--metadata-columns",
+        "Email",
+        "--ignore-columns",
+        "Class",
+    ]))
+    .expect("Cannot parse arguments");
+
+    let tb = generate(&options).expect("Cannot generate teams");
+    assert_eq!(tb.skills, vec!["Programming", "Graphics"]);
+    assert_eq!(tb.metadata, vec!["Email"]);
+
+    let options =
+        parse_generate_args(&args(&["--ignore-columns", "Age"])).expect("Cannot parse arguments");
+    assert!(matches!(generate(&options), Err(CliError::Usage(_))));
+
+    // Without column names the mapping is guessed
+    let options = parse_generate_args(&args(&[])).expect("Cannot parse arguments");
+    let tb = generate(&options).expect("Cannot generate teams");
+    assert_eq!(tb.skills, vec!["Programming", "Graphics"]);
+    assert_eq!(tb.metadata, vec!["Last name", "Email", "Class"]);
+}
+
+fn setup_categories_test() -> TeamBuilder {
+    SETUP_TEAMBUILDER_TEST!(TEST_FILE_CATEGORIES, path, tb);
+
+    tb.column_mapping = Some(ColumnMapping {
+        roles: vec![ColumnRole::Name, ColumnRole::Category, ColumnRole::Skill],
+    });
+    tb.process_file().expect("Cannot process file");
+    tb.calculate_teams_skill_level();
+    tb.sort_teams_by_skill_level(None);
+
+    tb
+}
+
+#[test]
+fn test_process_file_categories() {
+    let tb = setup_categories_test();
+
+    assert_eq!(tb.categories, vec!["Gender"]);
+    assert_eq!(tb.skills, vec!["Programming"]);
+    assert_eq!(tb.people[0].categories, vec!["F"]);
+}
+
+#[test]
+fn test_category_balance() {
+    let mut tb = setup_categories_test();
+
+    // Balancing the skills alone puts every F in the same team
+    tb.assign_people_with_strategy(&SnakeDraft, 4, None)
+        .expect("Cannot assign people");
+
+    let report = tb.get_category_balance_report();
+    assert_eq!(report[0].to_string(), "Gender F: 4, 0");
+    assert_eq!(report[1].to_string(), "Gender M: 0, 4");
+
+    tb.assign_people_with_strategy(&Optimizing { all_skills: false }, 4, None)
+        .expect("Cannot assign people");
+
+    let report = tb.get_category_balance_report();
+    assert_eq!(report[0].team_counts, vec![2, 2]);
+    assert_eq!(report[1].team_counts, vec![2, 2]);
+    assert!(approx_eq!(
+        f32,
+        tb.get_teams_spread(None),
+        0.0,
+        F32Margin::default()
+    ));
+}
+
+#[test]
+fn test_get_category_counts() {
+    let mut tb = setup_categories_test();
+    tb.assign_people_with_strategy(&RoundRobin, 2, None)
+        .expect("Cannot assign people");
+
+    let counts = get_category_counts(&tb.teams, 0);
+
+    assert_eq!(
+        counts.keys().cloned().collect::<Vec<&str>>(),
+        vec!["F", "M"]
+    );
+    assert_eq!(counts["F"].iter().sum::<usize>(), 4);
+    assert!(get_category_counts(&tb.teams, 1).is_empty());
+}
+
+#[test]
+fn test_find_team() {
+    let mut tb = TeamBuilder::new();
+    tb.team_names = vec!["Red".to_string()];
+
+    assert_eq!(tb.find_team("red"), Some(0));
+    assert_eq!(tb.find_team("Alfa"), Some(0));
+    assert_eq!(tb.find_team("Team Bravo"), Some(1));
+    assert_eq!(tb.find_team(" juliett "), Some(9));
+    assert_eq!(tb.find_team("Kilo"), None);
+}
+
+#[test]
+fn test_assign_people_with_pins() {
+    SETUP_TEAMBUILDER_TEST_AND_INIT!(TEST_FILE_EVEN, path, tb);
+
+    tb.calculate_teams_skill_level();
+    tb.sort_teams_by_skill_level(None);
+    tb.pin_person("Bonanni", 0);
+    tb.pin_person("Ricchiuti", 0);
+    tb.assign_people_to_team(3);
+
+    // The pinned people count, so the first team gets the weakest pick
+    assert_eq!(
+        get_surnames(&tb.teams[0]),
+        vec!["Ricchiuti", "Bonanni", "Reclus"]
+    );
+    assert_eq!(
+        get_surnames(&tb.teams[1]),
+        vec!["De Dominicis", "Pomettini", "Leotta"]
+    );
+
+    tb.unpin_person("Ricchiuti");
+    tb.assign_people_with_strategy(&Optimizing { all_skills: false }, 2, None)
+        .expect("Cannot assign people");
+
+    assert!(get_surnames(&tb.teams[0]).contains(&"Bonanni".to_string()));
+    assert!(tb.teams.iter().all(|team| team.people.len() == 2));
+
+    tb.optimize_teams(None);
+    assert_eq!(tb.teams[0].people[0].surname, "Bonanni");
+}
+
+#[test]
+fn test_assign_people_with_pins_over_team_size() {
+    SETUP_TEAMBUILDER_TEST_AND_INIT!(TEST_FILE_EVEN, path, tb);
+
+    tb.calculate_teams_skill_level();
+    tb.sort_teams_by_skill_level(None);
+
+    for surname in &["Reclus", "Leotta", "Pomettini"] {
+        tb.pin_person(surname, 1);
+    }
+
+    // Pins to teams that are not made are left out
+    tb.pin_person("Bonanni", 5);
+    tb.assign_people_with_strategy(&RoundRobin, 2, None)
+        .expect("Cannot assign people");
+
+    let sizes: Vec<usize> = tb.teams.iter().map(|team| team.people.len()).collect();
+
+    assert_eq!(sizes.iter().sum::<usize>(), 6);
+    assert_eq!(sizes[1], 3);
+    assert_eq!(
+        get_surnames(&tb.teams[1]),
+        vec!["Reclus", "Leotta", "Pomettini"]
+    );
+}
+
+#[test]
+fn test_pinned_people_are_not_in_reserve() {
+    SETUP_TEAMBUILDER_TEST_AND_INIT!(TEST_FILE_UNEVEN, path, tb);
+
+    tb.remainder_policy = RemainderPolicy::Reserve;
+    tb.calculate_teams_skill_level();
+    tb.sort_teams_by_skill_level(None);
+    tb.pin_person("Leotta", 1);
+    tb.assign_people_to_team(2);
+
+    assert_eq!(tb.teams[1].people[0].surname, "Leotta");
+    assert_eq!(tb.reserve.len(), 1);
+    assert_eq!(tb.reserve[0].surname, "Pomettini");
+}
+
+#[test]
+fn test_too_many_pinned_people() {
+    SETUP_TEAMBUILDER_TEST_AND_INIT!(TEST_FILE_UNEVEN, path, tb);
+
+    tb.remainder_policy = RemainderPolicy::Reserve;
+    tb.calculate_teams_skill_level();
+    tb.sort_teams_by_skill_level(None);
+
+    let surnames: Vec<String> = tb.people.iter().map(|x| x.surname.clone()).collect();
+
+    for (index, surname) in surnames.iter().enumerate() {
+        tb.pin_person(surname, index % 2);
+    }
+
+    assert_eq!(
+        tb.assign_people_with_strategy(&SnakeDraft, 2, None)
+            .unwrap_err()
+            .to_string(),
+        "cannot pin 5 people to teams with 4 places"
+    );
+
+    // Spreading the leftover person makes room for everybody
+    tb.remainder_policy = RemainderPolicy::Spread;
+    assert!(tb.assign_people_with_strategy(&SnakeDraft, 2, None).is_ok());
+}
+
+#[test]
+fn test_cli_pins() {
+    let pin_args = |pin: &str| {
+        get_args(&[
+            "--input",
+            TEST_FILE_EVEN,
+            "--teams",
+            "2",
+            "--pin",
+            "Bonanni=Bravo",
+            "--pin",
+            pin,
+        ])
+    };
+
+    let options = parse_generate_args(&pin_args("Ricchiuti = team bravo")).unwrap();
+    assert_eq!(
+        options.pins[1],
+        ("Ricchiuti".to_string(), "team bravo".to_string())
+    );
+
+    let tb = generate(&options).expect("Cannot generate teams");
+    let surnames = get_surnames(&tb.teams[1]);
+
+    assert!(surnames.contains(&"Bonanni".to_string()));
+    assert!(surnames.contains(&"Ricchiuti".to_string()));
+
+    let unknown_person = parse_generate_args(&pin_args("Nobody=Alfa")).unwrap();
+    assert!(generate(&unknown_person).is_err());
+
+    let team_not_made = parse_generate_args(&pin_args("Reclus=Charlie")).unwrap();
+    assert!(generate(&team_not_made).is_err());
+
+    assert!(parse_generate_args(&pin_args("Reclus")).is_err());
+}
+
+#[test]
+fn test_parse_round_csv() {
+    let round = parse_round_csv(&std::fs::read_to_string(TEST_FILE_EVEN_ROUND).unwrap())
+        .expect("Cannot parse round");
+
+    assert_eq!(
+        round,
+        vec![
+            vec!["Bonanni", "Reclus", "Leotta"],
+            vec!["Ricchiuti", "De Dominicis"]
+        ]
+    );
+
+    let comma = parse_round_csv("Name,Team\nTizio,Red\nCaio,Red\n").unwrap();
+    assert_eq!(comma, vec![vec!["Tizio", "Caio"]]);
+
+    assert!(parse_round_csv("Surname;Team\nTizio;Red\n").is_err());
+}
+
+#[test]
+fn test_past_pairings() {
+    let mut past = PastPairings::default();
+    let round = |teams: &[&[&str]]| -> Vec<Vec<String>> {
+        teams
+            .iter()
+            .map(|team| team.iter().map(|surname| surname.to_string()).collect())
+            .collect()
+    };
+
+    past.add_round(&round(&[&["Tizio", "Caio"], &["Sempronio", "Mevio"]]));
+    past.add_round(&round(&[&["Caio", "Tizio", "Mevio"], &["Sempronio"]]));
+
+    assert_eq!(past.rounds, 2);
+    assert_eq!(past.get_count("Tizio", "Caio"), 2);
+    assert_eq!(past.get_count("Caio", "Tizio"), 2);
+    assert_eq!(past.get_count("Mevio", "Caio"), 1);
+    assert_eq!(past.get_count("Sempronio", "Tizio"), 0);
+}
+
+#[test]
+fn test_avoid_repeated_pairings() {
+    SETUP_TEAMBUILDER_TEST_AND_INIT!(TEST_FILE_EVEN, path, tb);
+
+    tb.calculate_teams_skill_level();
+    tb.sort_teams_by_skill_level(None);
+    tb.assign_people_with_strategy(&Optimizing { all_skills: false }, 2, None)
+        .expect("Cannot assign people");
+
+    // Without earlier rounds there is nothing to report
+    assert!(tb.get_repeated_pairings().is_empty());
+
+    let json = generate_json(&tb).unwrap();
+    let first_round = parse_round_json(&json).expect("Cannot parse round");
+    tb.past_pairings.add_round(&first_round);
+
+    assert_eq!(tb.get_repeated_pairings().len(), 3);
+
+    tb.assign_people_with_strategy(&Optimizing { all_skills: false }, 2, None)
+        .expect("Cannot assign people");
+
+    let repeated = tb.get_repeated_pairings();
+    assert!(repeated.len() < 3);
+
+    if let Some(pairing) = repeated.first() {
+        assert!(pairing
+            .to_string()
+            .ends_with("were already together in 1 round"));
+    }
+
+    // Without weight the repeats are ignored and the first teams come back
+    tb.repeat_weight = 0.0;
+    tb.assign_people_with_strategy(&Optimizing { all_skills: false }, 2, None)
+        .expect("Cannot assign people");
+
+    assert_eq!(tb.get_repeated_pairings().len(), 3);
+}
+
+#[test]
+fn test_cli_previous_rounds() {
+    let options = parse_generate_args(&get_args(&[
+        "--input",
+        TEST_FILE_EVEN,
+        "--team-size",
+        "3",
+        "--previous",
+        TEST_FILE_EVEN_ROUND,
+        "--repeat-weight",
+        "2",
+    ]))
+    .expect("Cannot parse arguments");
+
+    let tb = generate(&options).expect("Cannot generate teams");
+
+    assert!(approx_eq!(f32, tb.repeat_weight, 2.0, F32Margin::default()));
+    assert_eq!(tb.past_pairings.rounds, 1);
+    assert_eq!(tb.past_pairings.get_count("Bonanni", "Leotta"), 1);
+    assert!(export(&tb, Format::Html).contains("<th>Repeated pairings</th>"));
+}
+
+#[test]
+fn test_generate_schedule() {
+    SETUP_TEAMBUILDER_TEST_AND_INIT!(TEST_FILE_EVEN, path, tb);
+
+    tb.calculate_teams_skill_level();
+    tb.sort_teams_by_skill_level(None);
+    tb.assign_people_with_strategy(&Optimizing { all_skills: false }, 2, None)
+        .expect("Cannot assign people");
+
+    let schedule = tb
+        .generate_schedule(&Optimizing { all_skills: false }, 3, None)
+        .expect("Cannot generate schedule");
+
+    assert_eq!(schedule.len(), 3);
+
+    for teams in &schedule {
+        let sizes: Vec<usize> = teams.iter().map(|team| team.people.len()).collect();
+        assert_eq!(sizes, vec![2, 2, 2]);
+    }
+
+    // Nobody is paired twice, so every round brings three new pairs
+    let mut pairings = PastPairings::default();
+
+    for teams in &schedule {
+        assert_eq!(pairings.count_repeats(teams), 0);
+        pairings.add_round(&get_round(teams));
+    }
+
+    assert_eq!(pairings.count_pairs(), 9);
+
+    // The schedule does not count as earlier rounds
+    assert_eq!(tb.past_pairings.rounds, 0);
+}
+
+#[test]
+fn test_generate_schedule_round_robin() {
+    SETUP_TEAMBUILDER_TEST_AND_INIT!(TEST_FILE_EVEN, path, tb);
+
+    tb.calculate_teams_skill_level();
+    tb.sort_teams_by_skill_level(None);
+    tb.assign_people_with_strategy(&RoundRobin, 2, None)
+        .expect("Cannot assign people");
+
+    let schedule = tb
+        .generate_schedule(&RoundRobin, 3, None)
+        .expect("Cannot generate schedule");
+    let mut pairings = PastPairings::default();
+
+    for teams in &schedule {
+        assert_eq!(pairings.count_repeats(teams), 0);
+        pairings.add_round(&get_round(teams));
+    }
+}
+
+#[test]
+fn test_generate_schedule_without_repeat_weight() {
+    SETUP_TEAMBUILDER_TEST_AND_INIT!(TEST_FILE_EVEN, path, tb);
+
+    tb.calculate_teams_skill_level();
+    tb.sort_teams_by_skill_level(None);
+    tb.assign_people_with_strategy(&Optimizing { all_skills: false }, 2, None)
+        .expect("Cannot assign people");
+    tb.repeat_weight = 0.0;
+
+    // The rounds of the schedule still avoid each other
+    let schedule = tb
+        .generate_schedule(&Optimizing { all_skills: false }, 3, None)
+        .expect("Cannot generate schedule");
+    let mut pairings = PastPairings::default();
+
+    for teams in &schedule {
+        assert_eq!(pairings.count_repeats(teams), 0);
+        pairings.add_round(&get_round(teams));
+    }
+}
+
+#[test]
+fn test_generate_schedule_without_teams() {
+    SETUP_TEAMBUILDER_TEST_AND_INIT!(TEST_FILE_EVEN, path, tb);
+
+    match tb.generate_schedule(&RoundRobin, 3, None) {
+        Err(TeamBuilderError::NoTeams) => (),
+        _ => panic!("Expected NoTeams"),
+    }
+}
+
+#[test]
+fn test_export_schedule() {
+    SETUP_TEAMBUILDER_TEST_AND_INIT!(TEST_FILE_EVEN, path, tb);
+
+    tb.calculate_teams_skill_level();
+    tb.sort_teams_by_skill_level(None);
+    tb.assign_people_to_team(3);
+
+    let schedule = tb
+        .generate_schedule(&Optimizing { all_skills: false }, 2, None)
+        .expect("Cannot generate schedule");
+
+    let html = generate_schedule_html(&tb, &schedule).unwrap();
+    assert!(html.contains("<tr><th>Rounds</th><td>2</td></tr>"));
+    assert!(html.contains("<h2>Round 2</h2>"));
+    assert_eq!(html.matches("<h3>Team Bravo</h3>").count(), 2);
+
+    let contents = spreadsheet_exporter::get_schedule_spreadsheet(&tb, &schedule)
+        .expect("Cannot generate spreadsheet");
+    assert!(contents.starts_with(b"PK"));
+}
+
+fn setup_preferences_test() -> TeamBuilder {
+    SETUP_TEAMBUILDER_TEST!(TEST_FILE_PREFERENCES, path, tb);
+
+    tb.column_mapping = Some(tb.guess_column_mapping().expect("Cannot guess mapping"));
+    tb.process_file().expect("Cannot process file");
+    tb.calculate_teams_skill_level();
+    tb.sort_teams_by_skill_level(None);
+    tb
+}
+
+#[test]
+fn test_process_file_preferences() {
+    let tb = setup_preferences_test();
+
+    assert_eq!(tb.skills.len(), 6);
+
+    let reclus = tb
+        .people
+        .iter()
+        .find(|person| person.surname == "Reclus")
+        .unwrap();
+    assert_eq!(reclus.preferences, vec!["Leotta", "Pomettini"]);
+}
+
+#[test]
+fn test_parse_preferences() {
+    let preferences =
+        parse_preferences(&std::fs::read_to_string(TEST_FILE_PREFERENCES_FILE).unwrap())
+            .expect("Cannot parse preferences");
+
+    assert_eq!(
+        preferences[0],
+        ("Bonanni".to_string(), vec!["Ricchiuti".to_string()])
+    );
+    assert_eq!(preferences[1].1, vec!["Pomettini", "Leotta"]);
+
+    match parse_preferences("Name;1;2;3;4\nTizio;Caio;Sempronio;Mevio;Filano\n") {
+        Err(TeamBuilderError::Malformed { line, .. }) => assert_eq!(line, 2),
+        result => panic!("Expected a malformed row, found {:?}", result),
+    }
+}
+
+#[test]
+fn test_load_preferences_file() {
+    SETUP_TEAMBUILDER_TEST_AND_INIT!(TEST_FILE_EVEN, path, tb);
+
+    tb.load_preferences_file(Path::new(TEST_FILE_PREFERENCES_FILE))
+        .expect("Cannot load preferences");
+
+    assert_eq!(tb.people[2].preferences, vec!["Ricchiuti"]);
+
+    tb.people.retain(|person| person.surname != "Bonanni");
+    assert!(tb
+        .load_preferences_file(Path::new(TEST_FILE_PREFERENCES_FILE))
+        .is_err());
+}
+
+#[test]
+fn test_unknown_preferences() {
+    let mut tb = TeamBuilder::new();
+    tb.people_file =
+        "Surname;Programming;Preference 1\nPomettini;3;Leotta\nLeotta;2;Nobody\n".to_string();
+    tb.column_mapping = Some(tb.guess_column_mapping().expect("Cannot guess mapping"));
+    assert_eq!(
+        tb.process_file().unwrap_err().to_string(),
+        "row 3: 'Leotta' prefers 'Nobody', but nobody is called that"
+    );
+
+    let result = parse_roster(
+        "{ \"skills\": [\"Programming\"], \"people\": [
+            { \"surname\": \"Tizio\", \"skill_levels\": [1], \"preferences\": [\"Nobody\"] },
+            { \"surname\": \"Caio\", \"skill_levels\": [2] }
+        ] }",
+    );
+    assert!(matches!(
+        result,
+        Err(TeamBuilderError::Malformed { line: 1, .. })
+    ));
+
+    // Ricchiuti is wanted by Bonanni in the preferences file
+    SETUP_TEAMBUILDER_TEST_AND_INIT!(TEST_FILE_EVEN, path, tb);
+    tb.people.retain(|person| person.surname != "Ricchiuti");
+    assert!(tb
+        .load_preferences_file(Path::new(TEST_FILE_PREFERENCES_FILE))
+        .is_err());
+}
+
+#[test]
+fn test_preference_satisfaction() {
+    let mut tb = setup_preferences_test();
+
+    // Granting every preference would put the three weakest people together
+    tb.assign_people_with_strategy(&Optimizing { all_skills: false }, 3, None)
+        .expect("Cannot assign people");
+    let balanced_spread = tb.get_teams_spread(None);
+    assert!(tb.get_preference_satisfaction() < 1.0);
+
+    tb.preference_weight = 100.0;
+    tb.assign_people_with_strategy(&Optimizing { all_skills: false }, 3, None)
+        .expect("Cannot assign people");
+
+    assert!(approx_eq!(
+        f32,
+        tb.get_preference_satisfaction(),
+        1.0,
+        F32Margin::default()
+    ));
+    assert!(tb.get_teams_spread(None) > balanced_spread);
+
+    let report = tb.get_preference_report();
+    assert_eq!(report.len(), 3);
+    assert_eq!(report[0].to_string(), "Reclus: 2 of 2 (Leotta, Pomettini)");
+}
+
+#[test]
+fn test_cli_preferences() {
+    let options = parse_generate_args(&get_args(&[
+        "--input",
+        TEST_FILE_PREFERENCES,
+        "--team-size",
+        "3",
+        "--preference-columns",
+        "Preference 1,Preference 2",
+        "--preference-weight",
+        "100",
+        "--format",
+        "html",
+    ]))
+    .expect("Cannot parse arguments");
+
+    assert_eq!(options.columns.preference.len(), 2);
+
+    let tb = generate(&options).expect("Cannot generate teams");
+
+    assert!(approx_eq!(
+        f32,
+        tb.get_preference_satisfaction(),
+        1.0,
+        F32Margin::default()
+    ));
+    assert!(export(&tb, Format::Html).contains("<th>Preferences granted</th><td>100%</td>"));
+
+    let negative_weight = parse_generate_args(&get_args(&[
+        "--input",
+        TEST_FILE_EVEN,
+        "--team-size",
+        "3",
+        "--preference-weight",
+        "-1",
+    ]));
+    assert!(negative_weight.is_err());
+
+    let infinite_weight = parse_generate_args(&get_args(&[
+        "--input",
+        TEST_FILE_EVEN,
+        "--team-size",
+        "3",
+        "--preference-weight",
+        "inf",
+    ]));
+    assert!(infinite_weight.is_err());
+}
+
+#[test]
+fn test_parse_coverage_rules() {
+    let rules = parse_coverage_rules("Programming>=3, 2:Graphics >= 2").unwrap();
+
+    assert_eq!(
+        rules,
+        vec![
+            CoverageRule {
+                skill: "Programming".to_string(),
+                level: 3,
+                count: 1,
+            },
+            CoverageRule {
+                skill: "Graphics".to_string(),
+                level: 2,
+                count: 2,
+            },
+        ]
+    );
+    assert_eq!(rules[1].to_string(), "at least 2 with Graphics >= 2");
+    assert!(parse_coverage_rules("").unwrap().is_empty());
+
+    for text in &["Programming", "0:Programming>=1", "x:Programming>=1", ">=2"] {
+        assert!(
+            CoverageRule::parse(text).is_err(),
+            "{} should not parse",
+            text
+        );
+    }
+}
+
+#[test]
+fn test_check_coverage() {
+    SETUP_TEAMBUILDER_TEST_AND_INIT!(TEST_FILE_EVEN, path, tb);
+
+    tb.coverage_rules = parse_coverage_rules("Programming>=2").unwrap();
+    assert!(tb.check_coverage(&[2, 2, 2]).is_ok());
+
+    let not_met = |tb: &TeamBuilder, team_sizes: &[usize]| match tb.check_coverage(team_sizes) {
+        Err(TeamBuilderError::CoverageNotMet { message, .. }) => message,
+        result => panic!("Expected the coverage not to be met, found {:?}", result),
+    };
+
+    tb.coverage_rules = parse_coverage_rules("Programming>=3").unwrap();
+    assert_eq!(not_met(&tb, &[3, 3]), "1 people qualify, 2 are needed");
+
+    tb.coverage_rules = parse_coverage_rules("3:Teamwork>=1").unwrap();
+    assert_eq!(not_met(&tb, &[2, 2, 2]), "the smallest team has 2 people");
+
+    tb.coverage_rules = parse_coverage_rules("Cooking>=1").unwrap();
+    assert_eq!(not_met(&tb, &[3, 3]), "there is no skill called 'Cooking'");
+
+    // Two of the three programmers pinned to the same team
+    tb.coverage_rules = parse_coverage_rules("Programming>=2").unwrap();
+    tb.pin_person("Ricchiuti", 0);
+    tb.pin_person("Bonanni", 0);
+    assert_eq!(not_met(&tb, &[2, 2, 2]), "2 people qualify, 3 are needed");
+    assert!(tb.check_coverage(&[3, 3]).is_ok());
+
+    tb.pins.clear();
+    tb.pin_person("Leotta", 0);
+    tb.pin_person("Reclus", 0);
+    assert_eq!(
+        not_met(&tb, &[2, 2, 2]),
+        "the people pinned to Team Alfa leave 0 places"
+    );
+}
+
+#[test]
+fn test_assign_people_with_coverage() {
+    SETUP_TEAMBUILDER_TEST_AND_INIT!(TEST_FILE_EVEN, path, tb);
+
+    tb.calculate_teams_skill_level();
+    tb.sort_teams_by_skill_level(None);
+
+    // Round robin leaves the last team without a programmer
+    tb.assign_people_with_strategy(&RoundRobin, 2, None)
+        .expect("Cannot assign people");
+    assert_eq!(get_surnames(&tb.teams[2]), vec!["De Dominicis", "Reclus"]);
+
+    tb.coverage_rules = parse_coverage_rules("Programming>=2").unwrap();
+    assert_eq!(tb.get_coverage_shortfalls().len(), 1);
+    assert_eq!(
+        tb.get_coverage_shortfalls()[0].to_string(),
+        "Team Charlie has 0 of 1 with Programming >= 2"
+    );
+
+    for strategy in get_strategies(0) {
+        tb.assign_people_with_strategy(strategy.as_ref(), 2, None)
+            .expect("Cannot assign people");
+        assert!(
+            tb.get_coverage_shortfalls().is_empty(),
+            "{}",
+            strategy.name()
+        );
+    }
+
+    tb.coverage_rules = parse_coverage_rules("Programming>=2, Graphics>=2").unwrap();
+
+    for strategy in get_strategies(0) {
+        tb.assign_people_with_strategy(strategy.as_ref(), 3, None)
+            .expect("Cannot assign people");
+        assert!(
+            tb.get_coverage_shortfalls().is_empty(),
+            "{}",
+            strategy.name()
+        );
+    }
+}
+
+#[test]
+fn test_coverage_keeps_qualified_people_out_of_reserve() {
+    SETUP_TEAMBUILDER_TEST_AND_INIT!(TEST_FILE_EVEN, path, tb);
+
+    tb.remainder_policy = RemainderPolicy::Reserve;
+    tb.coverage_rules = parse_coverage_rules("2:Narrative>=3").unwrap();
+    tb.calculate_teams_skill_level();
+    tb.sort_teams_by_skill_level(None);
+    tb.assign_people_with_strategy(&SnakeDraft, 4, None)
+        .expect("Cannot assign people");
+
+    let reserve: Vec<&str> = tb
+        .reserve
+        .iter()
+        .map(|person| person.surname.as_str())
+        .collect();
+    assert_eq!(reserve, vec!["Pomettini", "Ricchiuti"]);
+    assert!(tb.get_coverage_shortfalls().is_empty());
+
+    tb.coverage_rules = parse_coverage_rules("2:Programming>=3").unwrap();
+    assert!(matches!(
+        tb.assign_people_with_strategy(&SnakeDraft, 4, None),
+        Err(TeamBuilderError::CoverageNotMet { .. })
+    ));
+}
+
+#[test]
+fn test_cli_coverage() {
+    let output = std::env::temp_dir().join("team_builder_test_cli_coverage.html");
+    let require_args = |rule: &str| {
+        get_args(&[
+            "generate",
+            "--input",
+            TEST_FILE_EVEN,
+            "--teams",
+            "2",
+            "--require",
+            rule,
+            "--output",
+            output.to_str().unwrap(),
+        ])
+    };
+
+    assert_eq!(run(&require_args("Programming>=2")), EXIT_OK);
+    assert_eq!(run(&require_args("Programming>=3")), EXIT_COVERAGE_NOT_MET);
+    assert_eq!(run(&require_args("Programming")), EXIT_USAGE);
+
+    // Each rule can be met alone, but Tizio, Caio and Sempronio
+    // cannot all be apart in two teams
+    let shared_args = get_args(&[
+        "generate",
+        "--input",
+        TEST_FILE_COVERAGE_SHARED,
+        "--teams",
+        "2",
+        "--require",
+        "Programming>=3",
+        "--require",
+        "Graphics>=3",
+        "--require",
+        "Narrative>=3",
+        "--output",
+        output.to_str().unwrap(),
+    ]);
+    assert_eq!(run(&shared_args), EXIT_COVERAGE_NOT_MET);
+
+    std::fs::remove_file(&output).expect("Cannot remove output");
+}
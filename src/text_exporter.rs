@@ -0,0 +1,112 @@
+use crate::builder::*;
+
+// Each team as a Markdown table, ready to paste in a chat or a README,
+// followed by the people in reserve, the remainder policy and the seed
+pub fn generate_markdown(tb: &TeamBuilder) -> String {
+    let mut markdown = String::new();
+
+    for (index, team) in tb.teams.iter().enumerate() {
+        markdown.push_str(&format!(
+            "## {}\n\n",
+            escape_markdown(&tb.get_team_title(index))
+        ));
+        push_markdown_table(&mut markdown, &team.people);
+        markdown.push_str(&format!(
+            "| **Team average** | **{:.2}** |\n\n",
+            team.get_average_skill_level(None)
+        ));
+    }
+
+    if !tb.reserve.is_empty() {
+        markdown.push_str("## Reserve\n\n");
+        push_markdown_table(&mut markdown, &tb.reserve);
+        markdown.push('\n');
+    }
+
+    markdown.push_str(&format!("{}\n", tb.remainder_policy));
+
+    if let Some(seed) = tb.seed {
+        markdown.push_str(&format!("\nSeed: {}\n", seed));
+    }
+
+    markdown
+}
+
+fn push_markdown_table(markdown: &mut String, people: &[Person]) {
+    markdown.push_str("| Name | Average |\n");
+    markdown.push_str("| --- | ---: |\n");
+
+    for person in people {
+        markdown.push_str(&format!(
+            "| {} | {:.2} |\n",
+            escape_markdown(&person.surname),
+            person.average_skill_level
+        ));
+    }
+}
+
+// Each team with its people in columns, for places without Markdown,
+// followed by the people in reserve, the remainder policy and the seed
+pub fn generate_text(tb: &TeamBuilder) -> String {
+    let mut text = String::new();
+
+    // Every team uses the same width so the averages line up
+    let width = tb
+        .teams
+        .iter()
+        .flat_map(|team| &team.people)
+        .chain(&tb.reserve)
+        .map(|person| person.surname.chars().count())
+        .max()
+        .unwrap_or(0);
+
+    for (index, team) in tb.teams.iter().enumerate() {
+        text.push_str(&format!(
+            "{} (average {:.2})\n",
+            tb.get_team_title(index),
+            team.get_average_skill_level(None)
+        ));
+        push_text_people(&mut text, &team.people, width);
+        text.push('\n');
+    }
+
+    if !tb.reserve.is_empty() {
+        text.push_str("Reserve\n");
+        push_text_people(&mut text, &tb.reserve, width);
+        text.push('\n');
+    }
+
+    text.push_str(&format!("{}\n", tb.remainder_policy));
+
+    if let Some(seed) = tb.seed {
+        text.push_str(&format!("Seed: {}\n", seed));
+    }
+
+    text
+}
+
+fn push_text_people(text: &mut String, people: &[Person], width: usize) {
+    for person in people {
+        text.push_str(&format!(
+            "  {:<width$}  {:>5.2}\n",
+            person.surname,
+            person.average_skill_level,
+            width = width
+        ));
+    }
+}
+
+// Keeps names from breaking the table or being read as formatting
+fn escape_markdown(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for character in text.chars() {
+        if "\\|*_`[]<>".contains(character) {
+            escaped.push('\\');
+        }
+
+        escaped.push(character);
+    }
+
+    escaped
+}
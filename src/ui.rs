@@ -1,5 +1,14 @@
 use crate::builder::*;
+use crate::constraints::*;
+use crate::coverage::*;
+use crate::csv_exporter::*;
 use crate::html_exporter::*;
+use crate::json::*;
+use crate::preferences::*;
+use crate::random::*;
+use crate::spreadsheet_exporter::*;
+use crate::strategy::*;
+use crate::text_exporter::*;
 
 use itertools::Itertools;
 use iui::controls::*;
@@ -7,7 +16,8 @@ use iui::prelude::*;
 use std::cell::RefCell;
 use std::fs::File;
 use std::io::prelude::*;
-use std::process::Command;
+use std::path::Path;
+use std::process::{Command, Stdio};
 use std::rc::Rc;
 
 // State shared between UI components
@@ -15,6 +25,10 @@ struct State {
     teams: Vec<Team>,
     skills: Vec<String>,
     sort_by: Option<usize>,
+    strategy: usize,
+    fixed_number_of_teams: bool,
+    flexible_team_size: bool,
+    csv_delimiter: usize,
 }
 
 pub fn init_ui(tb: &Rc<RefCell<TeamBuilder>>) {
@@ -24,6 +38,10 @@ pub fn init_ui(tb: &Rc<RefCell<TeamBuilder>>) {
         teams: Vec::new(),
         skills: Vec::new(),
         sort_by: None,
+        strategy: 0,
+        fixed_number_of_teams: false,
+        flexible_team_size: false,
+        csv_delimiter: 0,
     }));
 
     let ui = UI::init().expect("Couldn't initialize UI library");
@@ -38,10 +56,33 @@ pub fn init_ui(tb: &Rc<RefCell<TeamBuilder>>) {
     let team_number_label = Label::new(&ui, "Team members: 2");
     let mut team_number_slider = Slider::new(&ui, 2, 10);
 
+    let mut fixed_number_of_teams_checkbox = Checkbox::new(&ui, "Fixed number of teams");
+
     selectors_hbox.append(&ui, team_number_label.clone(), LayoutStrategy::Compact);
     selectors_hbox.append(&ui, team_number_slider.clone(), LayoutStrategy::Stretchy);
+    selectors_hbox.append(
+        &ui,
+        fixed_number_of_teams_checkbox.clone(),
+        LayoutStrategy::Compact,
+    );
+
+    // Lets team sizes range from the value above up to a maximum
+    let mut team_size_range_hbox = HorizontalBox::new(&ui);
+    team_size_range_hbox.set_padded(&ui, true);
+
+    let mut flexible_team_size_checkbox = Checkbox::new(&ui, "Flexible team size");
+    let max_team_size_label = Label::new(&ui, "Up to: 2");
+    let mut max_team_size_slider = Slider::new(&ui, 2, 10);
 
-    let mut people_labels: Vec<Label> = Vec::new();
+    team_size_range_hbox.append(
+        &ui,
+        flexible_team_size_checkbox.clone(),
+        LayoutStrategy::Compact,
+    );
+    team_size_range_hbox.append(&ui, max_team_size_label.clone(), LayoutStrategy::Compact);
+    team_size_range_hbox.append(&ui, max_team_size_slider.clone(), LayoutStrategy::Stretchy);
+
+    let mut team_groups: Vec<Group> = Vec::new();
 
     let mut people_group_vbox = VerticalBox::new(&ui);
     people_group_vbox.set_padded(&ui, true);
@@ -50,6 +91,18 @@ pub fn init_ui(tb: &Rc<RefCell<TeamBuilder>>) {
     sort_by_skill_cb.append(&ui, "Sort by Average");
     sort_by_skill_cb.set_selected(&ui, 0);
 
+    let mut strategy_cb = Combobox::new(&ui);
+    for strategy in get_strategies(0) {
+        strategy_cb.append(&ui, strategy.name());
+    }
+    strategy_cb.set_selected(&ui, 0);
+
+    let mut remainder_policy_cb = Combobox::new(&ui);
+    for policy in REMAINDER_POLICIES.iter() {
+        remainder_policy_cb.append(&ui, &policy.to_string());
+    }
+    remainder_policy_cb.set_selected(&ui, 0);
+
     // FIXME: Must refactor
     // Creates two columns and five rows for the teams
     let mut counter = 0;
@@ -57,16 +110,19 @@ pub fn init_ui(tb: &Rc<RefCell<TeamBuilder>>) {
         let mut people_group_hbox = HorizontalBox::new(&ui);
         people_group_hbox.set_padded(&ui, true);
         for _ in 0..2 {
-            let mut group = Group::new(&ui, &format!("Team {}", TEAM_NAMES[counter]));
-            let label = Label::new(&ui, "");
-            people_labels.push(label.clone());
-            group.set_child(&ui, label);
+            let mut group = Group::new(&ui, &format!("Team {}", get_team_name(counter)));
+            group.set_child(&ui, VerticalBox::new(&ui));
+            team_groups.push(group.clone());
             people_group_hbox.append(&ui, group, LayoutStrategy::Stretchy);
             counter += 1;
         }
         people_group_vbox.append(&ui, people_group_hbox, LayoutStrategy::Stretchy);
     }
 
+    // Filled with one spinbox per skill once the file is loaded
+    let mut weights_hbox = HorizontalBox::new(&ui);
+    weights_hbox.set_padded(&ui, true);
+
     let mut load_file_button = Button::new(&ui, "Load CSV file");
 
     load_file_button.on_clicked(&ui, {
@@ -75,6 +131,7 @@ pub fn init_ui(tb: &Rc<RefCell<TeamBuilder>>) {
         let tb = tb.clone();
         let state = state.clone();
         let sort_by_skill_cb = sort_by_skill_cb.clone();
+        let mut weights_hbox = weights_hbox.clone();
         move |button| {
             // TODO: Due to a bug, you cannot reload the file
             if !tb.borrow().people_file.is_empty() {
@@ -91,103 +148,341 @@ pub fn init_ui(tb: &Rc<RefCell<TeamBuilder>>) {
 
             match tb.borrow_mut().load_file(&file_path) {
                 Ok(_) => (),
-                Err(_) => {
-                    window.modal_msg(&ui, "Warning", "Please enter a valid file");
+                Err(err) => {
+                    window.modal_msg(
+                        &ui,
+                        "Warning",
+                        &format!("Please enter a valid file: {}", err),
+                    );
                     return;
                 }
             };
 
-            match tb.borrow_mut().process_file() {
-                Ok(_) => (),
-                Err(_) => {
-                    window.modal_msg(&ui, "Warning", "Please enter a valid CSV file");
-                    return;
-                }
-            }
-
-            // TODO: Bug, appends skills without resetting them
-
-            // Add skills from file to the global state
-            state.borrow_mut().skills = tb.borrow().skills.clone();
-
-            // Add each skill to the ComboBox
-            for skill in &tb.borrow().skills {
-                sort_by_skill_cb.append(&ui, &format!("Sort by {}", skill));
+            if is_json_path(&file_path) {
+                on_roster_loaded(
+                    &ui,
+                    &window,
+                    &tb,
+                    &state,
+                    &mut sort_by_skill_cb.clone(),
+                    &mut weights_hbox,
+                    button,
+                    &file_path,
+                );
+                return;
             }
 
-            tb.borrow_mut().calculate_teams_skill_level();
+            // CSV columns are picked by the user before reading the people
+            show_column_mapping_window(&ui, &tb, {
+                let ui = ui.clone();
+                let window = window.clone();
+                let tb = tb.clone();
+                let state = state.clone();
+                let mut sort_by_skill_cb = sort_by_skill_cb.clone();
+                let mut weights_hbox = weights_hbox.clone();
+                let mut button = button.clone();
+                move |mapping| {
+                    tb.borrow_mut().column_mapping = Some(mapping);
+                    on_roster_loaded(
+                        &ui,
+                        &window,
+                        &tb,
+                        &state,
+                        &mut sort_by_skill_cb,
+                        &mut weights_hbox,
+                        &mut button,
+                        &file_path,
+                    );
+                }
+            });
+        }
+    });
 
-            button.set_text(
+    // Updates the number of teams based on slider's value
+    team_number_slider.on_changed(&ui, {
+        let ui = ui.clone();
+        let state = state.clone();
+        let mut team_number_label = team_number_label.clone();
+        move |val| {
+            team_number_label.set_text(
                 &ui,
-                &format!(
-                    "Loaded {}",
-                    // TODO: Needs refactor
-                    &file_path.file_name().unwrap().to_str().unwrap()
-                ),
+                &get_team_number_text(state.borrow().fixed_number_of_teams, val),
             );
         }
     });
 
-    // Updates the number of teams based on slider's value
-    team_number_slider.on_changed(&ui, {
+    // Switches the slider between people per team and number of teams
+    fixed_number_of_teams_checkbox.on_toggled(&ui, {
         let ui = ui.clone();
+        let state = state.clone();
+        let team_number_slider = team_number_slider.clone();
         let mut team_number_label = team_number_label;
+        move |checked| {
+            state.borrow_mut().fixed_number_of_teams = checked;
+            team_number_label.set_text(
+                &ui,
+                &get_team_number_text(checked, team_number_slider.value(&ui)),
+            );
+        }
+    });
+
+    flexible_team_size_checkbox.on_toggled(&ui, {
+        let state = state.clone();
+        move |checked| {
+            state.borrow_mut().flexible_team_size = checked;
+        }
+    });
+
+    max_team_size_slider.on_changed(&ui, {
+        let ui = ui.clone();
+        let mut max_team_size_label = max_team_size_label;
         move |val| {
-            team_number_label.set_text(&ui, &format!("Team members: {}", val));
+            max_team_size_label.set_text(&ui, &format!("Up to: {}", val));
+        }
+    });
+
+    // The same seed always gives the same teams
+    let mut seed_hbox = HorizontalBox::new(&ui);
+    seed_hbox.set_padded(&ui, true);
+
+    let seed_entry = Entry::new(&ui);
+    let mut new_seed_button = Button::new(&ui, "New seed");
+
+    new_seed_button.on_clicked(&ui, {
+        let ui = ui.clone();
+        let mut seed_entry = seed_entry.clone();
+        move |_| {
+            seed_entry.set_value(&ui, &seed_from_time().to_string());
+        }
+    });
+
+    seed_hbox.append(&ui, Label::new(&ui, "Seed"), LayoutStrategy::Compact);
+    seed_hbox.append(&ui, seed_entry.clone(), LayoutStrategy::Stretchy);
+    seed_hbox.append(&ui, new_seed_button, LayoutStrategy::Compact);
+
+    // How much the teammate preferences count against skill balance
+    let mut preference_weight_spinbox = Spinbox::new(&ui, 0, 10);
+    preference_weight_spinbox.set_value(&ui, 1);
+
+    preference_weight_spinbox.on_changed(&ui, {
+        let tb = tb.clone();
+        move |value| {
+            tb.borrow_mut().preference_weight = value as f32;
         }
     });
 
+    seed_hbox.append(&ui, Label::new(&ui, "Preferences"), LayoutStrategy::Compact);
+    seed_hbox.append(&ui, preference_weight_spinbox, LayoutStrategy::Compact);
+
+    // How much the pairings of the previous rounds count against skill balance
+    let mut repeat_weight_spinbox = Spinbox::new(&ui, 0, 10);
+    repeat_weight_spinbox.set_value(&ui, 1);
+
+    repeat_weight_spinbox.on_changed(&ui, {
+        let tb = tb.clone();
+        move |value| {
+            tb.borrow_mut().repeat_weight = value as f32;
+        }
+    });
+
+    seed_hbox.append(&ui, Label::new(&ui, "Repeats"), LayoutStrategy::Compact);
+    seed_hbox.append(&ui, repeat_weight_spinbox, LayoutStrategy::Compact);
+
+    // Such as "Programming>=3, 2:Graphics>=2"
+    let mut coverage_hbox = HorizontalBox::new(&ui);
+    coverage_hbox.set_padded(&ui, true);
+
+    let coverage_entry = Entry::new(&ui);
+
+    coverage_hbox.append(
+        &ui,
+        Label::new(&ui, "Every team needs"),
+        LayoutStrategy::Compact,
+    );
+    coverage_hbox.append(&ui, coverage_entry.clone(), LayoutStrategy::Stretchy);
+
     let mut generate_button = Button::new(&ui, "Generate Teams");
+    let spread_label = Label::new(&ui, "Spread between teams: -");
+    let balance_report_label = Label::new(&ui, "");
+    let leftover_label = Label::new(&ui, "");
+    let repeated_pairings_label = Label::new(&ui, "");
 
     generate_button.on_clicked(&ui, {
         let ui = ui.clone();
         let window = window.clone();
         let team_number_slider = team_number_slider;
+        let max_team_size_slider = max_team_size_slider;
         let state = state.clone();
         let tb = tb.clone();
+        let mut spread_label = spread_label.clone();
+        let mut balance_report_label = balance_report_label.clone();
+        let mut leftover_label = leftover_label.clone();
+        let mut repeated_pairings_label = repeated_pairings_label.clone();
+        let mut seed_entry = seed_entry;
+        let coverage_entry = coverage_entry;
         move |_| {
             if tb.borrow().people.is_empty() {
                 window.modal_msg(&ui, "Warning", "Please load a CSV file first");
                 return;
             }
 
+            // Use the seed in the entry, or make a new one if there is none
+            let seed = match seed_entry.value(&ui).trim().parse::<u64>() {
+                Ok(seed) => seed,
+                Err(_) => {
+                    let seed = seed_from_time();
+                    seed_entry.set_value(&ui, &seed.to_string());
+                    seed
+                }
+            };
+
+            tb.borrow_mut().seed = Some(seed);
+
+            match parse_coverage_rules(&coverage_entry.value(&ui)) {
+                Ok(rules) => tb.borrow_mut().coverage_rules = rules,
+                Err(err) => {
+                    window.modal_msg(&ui, "Warning", &err.to_string());
+                    return;
+                }
+            }
+
             // Do stuff with teams data
             tb.borrow_mut()
                 .sort_teams_by_skill_level(state.borrow().sort_by);
-            tb.borrow_mut()
-                .assign_people_to_team(team_number_slider.value(&ui) as usize);
 
-            // Cleans the value of every label
-            for label in people_labels.iter_mut() {
-                label.set_text(&ui, "");
+            let strategies = get_strategies(seed);
+            let strategy = strategies[state.borrow().strategy].as_ref();
+            let team_number = team_number_slider.value(&ui) as usize;
+            let sort_by = state.borrow().sort_by;
+            let mut people_per_team = None;
+
+            let result = if state.borrow().fixed_number_of_teams {
+                tb.borrow_mut()
+                    .assign_people_to_number_of_teams(strategy, team_number, sort_by)
+            } else if state.borrow().flexible_team_size {
+                let max_size = team_number.max(max_team_size_slider.value(&ui) as usize);
+
+                tb.borrow_mut().assign_people_in_size_range(
+                    strategy,
+                    team_number,
+                    max_size,
+                    sort_by,
+                )
+            } else {
+                people_per_team = Some(team_number);
+
+                tb.borrow_mut()
+                    .assign_people_with_strategy(strategy, team_number, sort_by)
+            };
+
+            if let Err(err) = result {
+                window.modal_msg(&ui, "Warning", &err.to_string());
+                return;
             }
 
-            state.borrow_mut().teams = tb.borrow().teams.clone();
+            leftover_label.set_text(&ui, &get_leftover_text(&tb.borrow(), people_per_team));
 
-            // Assigns the teams on each label
-            let mut counter = 0;
-            for team in tb.borrow().teams.iter().map(|team| &team.people) {
-                let surnames: Vec<String> = team
+            let spread = tb.borrow().get_teams_spread(state.borrow().sort_by);
+            spread_label.set_text(&ui, &format!("Spread between teams: {:.2}", spread));
+
+            // Skill averages followed by the category counts of each team
+            let skill_balance = tb
+                .borrow()
+                .get_skill_balance_report()
+                .iter()
+                .map(|balance| balance.to_string())
+                .collect::<Vec<String>>();
+            let category_balance = tb
+                .borrow()
+                .get_category_balance_report()
+                .iter()
+                .map(|balance| balance.to_string())
+                .collect::<Vec<String>>();
+            let balance_report = skill_balance.iter().chain(&category_balance).join("\n");
+            let preference_report = tb.borrow().get_preference_report();
+
+            let balance_report = if preference_report.is_empty() {
+                balance_report
+            } else {
+                format!(
+                    "{}\nPreferences granted: {:.0}%\n{}",
+                    balance_report,
+                    tb.borrow().get_preference_satisfaction() * 100.0,
+                    preference_report.iter().join("\n")
+                )
+            };
+            balance_report_label.set_text(&ui, &balance_report);
+
+            if tb.borrow().past_pairings.rounds > 0 {
+                let repeated = tb.borrow().get_repeated_pairings();
+                let repeated_text = format!(
+                    "Repeated pairings: {}\n{}",
+                    repeated.len(),
+                    repeated.iter().join("\n")
+                );
+                repeated_pairings_label.set_text(&ui, repeated_text.trim_end());
+            }
+
+            let conflicts = tb.borrow().get_constraint_conflicts();
+
+            if !conflicts.is_empty() {
+                let conflicts = conflicts
                     .iter()
-                    .map(|person| {
-                        format!(
-                            "{} [{:.1}]",
-                            person.surname.clone(),
-                            person.average_skill_level
-                        )
-                    })
-                    .collect();
-
-                let surname_list = surnames.iter().join(", ");
-                people_labels[counter].set_text(&ui, &surname_list);
-
-                counter += 1;
+                    .map(|conflict| conflict.to_string())
+                    .join("\n");
+                window.modal_msg(&ui, "Conflicting constraints", &conflicts);
+            }
+
+            let shortfalls = tb.borrow().get_coverage_shortfalls();
+
+            if !shortfalls.is_empty() {
+                let shortfalls = shortfalls.iter().join("\n");
+                window.modal_msg(&ui, "Skills not covered", &shortfalls);
+            }
+
+            state.borrow_mut().teams = tb.borrow().teams.clone();
+
+            // Shows the people of each team, the groups without a team are emptied
+            for (index, group) in team_groups.iter_mut().enumerate() {
+                fill_team_group(&ui, group, &tb, index);
+            }
+        }
+    });
+
+    // Earlier rounds can be loaded one after the other
+    let mut load_previous_round_button = Button::new(&ui, "Load previous round");
+
+    load_previous_round_button.on_clicked(&ui, {
+        let ui = ui.clone();
+        let window = window.clone();
+        let tb = tb.clone();
+        move |button| {
+            let file_path = match window.open_file(&ui) {
+                Some(path) => path,
+                None => return,
+            };
+
+            match tb.borrow_mut().load_previous_round_file(&file_path) {
+                Ok(_) => {
+                    let rounds = tb.borrow().past_pairings.rounds;
+                    button.set_text(&ui, &format!("Load previous round ({} loaded)", rounds));
+                }
+                Err(err) => {
+                    window.modal_msg(
+                        &ui,
+                        "Warning",
+                        &format!("Please enter a CSV or JSON export: {}", err),
+                    );
+                }
             }
         }
     });
 
     program_vbox.append(&ui, load_file_button, LayoutStrategy::Stretchy);
+    program_vbox.append(&ui, load_previous_round_button, LayoutStrategy::Compact);
     program_vbox.append(&ui, selectors_hbox, LayoutStrategy::Compact);
+    program_vbox.append(&ui, team_size_range_hbox, LayoutStrategy::Compact);
+    program_vbox.append(&ui, weights_hbox, LayoutStrategy::Compact);
 
     // Updates the value of the sorting variable
     sort_by_skill_cb.clone().on_selected(&ui, {
@@ -202,9 +497,38 @@ pub fn init_ui(tb: &Rc<RefCell<TeamBuilder>>) {
         }
     });
 
+    // Updates what happens to people that do not fit in the teams
+    remainder_policy_cb.clone().on_selected(&ui, {
+        let tb = tb.clone();
+        move |index| {
+            tb.borrow_mut().remainder_policy = REMAINDER_POLICIES[index as usize];
+        }
+    });
+
+    // Updates the assignment strategy
+    strategy_cb.clone().on_selected(&ui, {
+        let state = state.clone();
+        move |index| {
+            state.borrow_mut().strategy = index as usize;
+        }
+    });
+
+    program_vbox.append(&ui, seed_hbox, LayoutStrategy::Compact);
+    program_vbox.append(&ui, coverage_hbox, LayoutStrategy::Compact);
     program_vbox.append(&ui, generate_button, LayoutStrategy::Compact);
     program_vbox.append(&ui, sort_by_skill_cb, LayoutStrategy::Compact);
+    program_vbox.append(&ui, strategy_cb, LayoutStrategy::Compact);
+    program_vbox.append(&ui, remainder_policy_cb, LayoutStrategy::Compact);
+    program_vbox.append(&ui, leftover_label, LayoutStrategy::Compact);
+    program_vbox.append(&ui, spread_label, LayoutStrategy::Compact);
+    program_vbox.append(&ui, balance_report_label, LayoutStrategy::Compact);
+    program_vbox.append(&ui, repeated_pairings_label, LayoutStrategy::Compact);
     program_vbox.append(&ui, HorizontalSeparator::new(&ui), LayoutStrategy::Compact);
+    program_vbox.append(
+        &ui,
+        Label::new(&ui, "Tick a person to keep them in their team"),
+        LayoutStrategy::Compact,
+    );
     program_vbox.append(&ui, people_group_vbox, LayoutStrategy::Compact);
     program_vbox.append(&ui, HorizontalSeparator::new(&ui), LayoutStrategy::Compact);
 
@@ -216,6 +540,7 @@ pub fn init_ui(tb: &Rc<RefCell<TeamBuilder>>) {
         let ui = ui.clone();
         let window = window.clone();
         let state = state.clone();
+        let tb = tb.clone();
         move |_| {
             if state.borrow().teams.is_empty() {
                 window.modal_msg(&ui, "Warning", "Please generate the teams first");
@@ -232,7 +557,7 @@ pub fn init_ui(tb: &Rc<RefCell<TeamBuilder>>) {
                 }
             };
 
-            let html_output = generate_html(&state.borrow().teams).expect("Cannot generate HTML");
+            let html_output = generate_html(&tb.borrow()).expect("Cannot generate HTML");
 
             let mut file = File::create(&save_file_path).expect("Cannot create file");
             file.write_all(html_output.as_bytes())
@@ -246,11 +571,56 @@ pub fn init_ui(tb: &Rc<RefCell<TeamBuilder>>) {
     });
     exporters_hbox.append(&ui, generate_html_table_button, LayoutStrategy::Stretchy);
 
+    let mut csv_delimiter_cb = Combobox::new(&ui);
+    for (name, _) in CSV_DELIMITERS.iter() {
+        csv_delimiter_cb.append(&ui, name);
+    }
+    csv_delimiter_cb.set_selected(&ui, 0);
+
+    csv_delimiter_cb.on_selected(&ui, {
+        let state = state.clone();
+        move |index| {
+            state.borrow_mut().csv_delimiter = index as usize;
+        }
+    });
+
+    let mut generate_csv_button = Button::new(&ui, "Export CSV Table");
+    generate_csv_button.on_clicked(&ui, {
+        let ui = ui.clone();
+        let window = window.clone();
+        let state = state.clone();
+        let tb = tb.clone();
+        move |_| {
+            if state.borrow().teams.is_empty() {
+                window.modal_msg(&ui, "Warning", "Please generate the teams first");
+                return;
+            }
+
+            let save_file_path = match window.save_file(&ui) {
+                Some(path) => path.with_extension("csv"),
+                None => {
+                    window.modal_msg(&ui, "Warning", "Please enter a valid file name");
+                    return;
+                }
+            };
+
+            let (_, delimiter) = CSV_DELIMITERS[state.borrow().csv_delimiter];
+            let csv_output = generate_csv(&tb.borrow(), delimiter).expect("Cannot generate CSV");
+
+            if let Err(err) = std::fs::write(&save_file_path, csv_output) {
+                window.modal_msg(&ui, "Warning", &format!("Cannot export CSV: {}", err));
+            }
+        }
+    });
+    exporters_hbox.append(&ui, generate_csv_button, LayoutStrategy::Stretchy);
+    exporters_hbox.append(&ui, csv_delimiter_cb, LayoutStrategy::Compact);
+
     let mut generate_csv_table_button = Button::new(&ui, "Export Excel Table");
     generate_csv_table_button.on_clicked(&ui, {
         let ui = ui.clone();
         let window = window.clone();
-        let state = state;
+        let state = state.clone();
+        let tb = tb.clone();
         // TODO: Refactor code to avoid duplication
         move |_| {
             if state.borrow().teams.is_empty() {
@@ -258,18 +628,532 @@ pub fn init_ui(tb: &Rc<RefCell<TeamBuilder>>) {
                 return;
             }
 
-            // let save_file_path = window.save_file(&ui);
+            let save_file_path = window.save_file(&ui);
+
+            let save_file_path = match save_file_path {
+                Some(path) => path.with_extension("xlsx"),
+                None => {
+                    window.modal_msg(&ui, "Warning", "Please enter a valid file name");
+                    return;
+                }
+            };
 
-            // TODO: Must show warning if file is empty
-            // generate_spreadsheet(&state.borrow().teams);
-            // TODO: Ask user where to save file
+            if let Err(err) = generate_spreadsheet(&tb.borrow(), &save_file_path) {
+                window.modal_msg(
+                    &ui,
+                    "Warning",
+                    &format!("Cannot export the spreadsheet: {}", err),
+                );
+            }
         }
     });
     exporters_hbox.append(&ui, generate_csv_table_button, LayoutStrategy::Stretchy);
 
+    let mut generate_json_button = Button::new(&ui, "Export JSON");
+    generate_json_button.on_clicked(&ui, {
+        let ui = ui.clone();
+        let window = window.clone();
+        let state = state.clone();
+        let tb = tb.clone();
+        move |_| {
+            if state.borrow().teams.is_empty() {
+                window.modal_msg(&ui, "Warning", "Please generate the teams first");
+                return;
+            }
+
+            let save_file_path = match window.save_file(&ui) {
+                Some(path) => path.with_extension("json"),
+                None => {
+                    window.modal_msg(&ui, "Warning", "Please enter a valid file name");
+                    return;
+                }
+            };
+
+            let json_output = generate_json(&tb.borrow()).expect("Cannot generate JSON");
+
+            if let Err(err) = std::fs::write(&save_file_path, json_output) {
+                window.modal_msg(&ui, "Warning", &format!("Cannot export JSON: {}", err));
+            }
+        }
+    });
+    exporters_hbox.append(&ui, generate_json_button, LayoutStrategy::Stretchy);
+
+    // Several rounds with the sizes of the generated teams,
+    // mixing people as much as the balance allows
+    let mut schedule_hbox = HorizontalBox::new(&ui);
+    schedule_hbox.set_padded(&ui, true);
+
+    let mut rounds_spinbox = Spinbox::new(&ui, 2, 10);
+    rounds_spinbox.set_value(&ui, 3);
+
+    let mut generate_schedule_html_button = Button::new(&ui, "Export schedule HTML");
+    generate_schedule_html_button.on_clicked(&ui, {
+        let ui = ui.clone();
+        let window = window.clone();
+        let state = state.clone();
+        let tb = tb.clone();
+        let rounds_spinbox = rounds_spinbox.clone();
+        move |_| {
+            if state.borrow().teams.is_empty() {
+                window.modal_msg(&ui, "Warning", "Please generate the teams first");
+                return;
+            }
+
+            let save_file_path = match window.save_file(&ui) {
+                Some(path) => path.with_extension("html"),
+                None => {
+                    window.modal_msg(&ui, "Warning", "Please enter a valid file name");
+                    return;
+                }
+            };
+
+            let schedule = match get_schedule(&tb, &state, rounds_spinbox.value(&ui) as usize) {
+                Ok(schedule) => schedule,
+                Err(err) => {
+                    window.modal_msg(&ui, "Warning", &err.to_string());
+                    return;
+                }
+            };
+            let html_output =
+                generate_schedule_html(&tb.borrow(), &schedule).expect("Cannot generate HTML");
+
+            if let Err(err) = std::fs::write(&save_file_path, html_output) {
+                window.modal_msg(&ui, "Warning", &format!("Cannot export HTML: {}", err));
+                return;
+            }
+
+            Command::new("open")
+                .arg(save_file_path)
+                .output()
+                .expect("Cannot run open command");
+        }
+    });
+
+    let mut generate_schedule_spreadsheet_button = Button::new(&ui, "Export schedule Excel");
+    generate_schedule_spreadsheet_button.on_clicked(&ui, {
+        let ui = ui.clone();
+        let window = window.clone();
+        let state = state.clone();
+        let tb = tb.clone();
+        let rounds_spinbox = rounds_spinbox.clone();
+        move |_| {
+            if state.borrow().teams.is_empty() {
+                window.modal_msg(&ui, "Warning", "Please generate the teams first");
+                return;
+            }
+
+            let save_file_path = match window.save_file(&ui) {
+                Some(path) => path.with_extension("xlsx"),
+                None => {
+                    window.modal_msg(&ui, "Warning", "Please enter a valid file name");
+                    return;
+                }
+            };
+
+            let schedule = match get_schedule(&tb, &state, rounds_spinbox.value(&ui) as usize) {
+                Ok(schedule) => schedule,
+                Err(err) => {
+                    window.modal_msg(&ui, "Warning", &err.to_string());
+                    return;
+                }
+            };
+
+            if let Err(err) =
+                generate_schedule_spreadsheet(&tb.borrow(), &schedule, &save_file_path)
+            {
+                window.modal_msg(
+                    &ui,
+                    "Warning",
+                    &format!("Cannot export the spreadsheet: {}", err),
+                );
+            }
+        }
+    });
+
+    schedule_hbox.append(&ui, Label::new(&ui, "Rounds"), LayoutStrategy::Compact);
+    schedule_hbox.append(&ui, rounds_spinbox, LayoutStrategy::Compact);
+    schedule_hbox.append(&ui, generate_schedule_html_button, LayoutStrategy::Stretchy);
+    schedule_hbox.append(
+        &ui,
+        generate_schedule_spreadsheet_button,
+        LayoutStrategy::Stretchy,
+    );
+
+    let mut copy_to_clipboard_button = Button::new(&ui, "Copy to clipboard");
+    copy_to_clipboard_button.on_clicked(&ui, {
+        let ui = ui.clone();
+        let window = window.clone();
+        let state = state;
+        let tb = tb.clone();
+        move |_| {
+            if state.borrow().teams.is_empty() {
+                window.modal_msg(&ui, "Warning", "Please generate the teams first");
+                return;
+            }
+
+            let text_output = generate_text(&tb.borrow());
+
+            if let Err(err) = copy_to_clipboard(&text_output) {
+                window.modal_msg(&ui, "Warning", &format!("Cannot copy the teams: {}", err));
+            }
+        }
+    });
+    exporters_hbox.append(&ui, copy_to_clipboard_button, LayoutStrategy::Stretchy);
+
     program_vbox.append(&ui, exporters_hbox, LayoutStrategy::Compact);
+    program_vbox.append(&ui, schedule_hbox, LayoutStrategy::Compact);
 
     window.set_child(&ui, program_vbox);
     window.show(&ui);
     ui.main();
 }
+
+// Reads the loaded roster, then loads the constraints next to it
+// and fills the controls that depend on the skills
+#[allow(clippy::too_many_arguments)]
+fn on_roster_loaded(
+    ui: &UI,
+    window: &Window,
+    tb: &Rc<RefCell<TeamBuilder>>,
+    state: &Rc<RefCell<State>>,
+    sort_by_skill_cb: &mut Combobox,
+    weights_hbox: &mut HorizontalBox,
+    button: &mut Button,
+    file_path: &Path,
+) {
+    let result = if is_json_path(file_path) {
+        tb.borrow_mut().process_json()
+    } else {
+        tb.borrow_mut().process_file()
+    };
+
+    match result {
+        Ok(_) => (),
+        Err(err) => {
+            // Forget the file so the user can load a fixed one
+            tb.borrow_mut().people_file.clear();
+            window.modal_msg(
+                ui,
+                "Warning",
+                &format!("Please enter a valid CSV file: {}", err),
+            );
+            return;
+        }
+    }
+
+    // Constraints are optional and sit next to the roster file
+    let constraints_path = get_constraints_path(file_path);
+
+    if constraints_path.exists() {
+        if let Err(err) = tb.borrow_mut().load_constraints_file(&constraints_path) {
+            window.modal_msg(
+                ui,
+                "Warning",
+                &format!("Cannot load the constraints file: {}", err),
+            );
+        }
+    }
+
+    // So are the preferences, when they are not in the roster itself
+    let preferences_path = get_preferences_path(file_path);
+
+    if preferences_path.exists() {
+        if let Err(err) = tb.borrow_mut().load_preferences_file(&preferences_path) {
+            window.modal_msg(
+                ui,
+                "Warning",
+                &format!("Cannot load the preferences file: {}", err),
+            );
+        }
+    }
+
+    // TODO: Bug, appends skills without resetting them
+
+    // Add skills from file to the global state
+    state.borrow_mut().skills = tb.borrow().skills.clone();
+
+    // Add each skill to the ComboBox
+    for skill in &tb.borrow().skills {
+        sort_by_skill_cb.append(ui, &format!("Sort by {}", skill));
+    }
+
+    // Add a spinbox for the weight of each skill
+    let skills = tb.borrow().skills.clone();
+    for (index, skill) in skills.iter().enumerate() {
+        let mut weight_spinbox = Spinbox::new(ui, 0, 10);
+        weight_spinbox.set_value(ui, tb.borrow().skill_weights[index].round() as i32);
+
+        weight_spinbox.on_changed(ui, {
+            let tb = tb.clone();
+            move |value| {
+                tb.borrow_mut().skill_weights[index] = value as f32;
+                tb.borrow_mut().calculate_teams_skill_level();
+            }
+        });
+
+        weights_hbox.append(ui, Label::new(ui, skill), LayoutStrategy::Compact);
+        weights_hbox.append(ui, weight_spinbox, LayoutStrategy::Stretchy);
+    }
+
+    tb.borrow_mut().calculate_teams_skill_level();
+
+    button.set_text(
+        ui,
+        &format!(
+            "Loaded {}",
+            // TODO: Needs refactor
+            &file_path.file_name().unwrap().to_str().unwrap()
+        ),
+    );
+}
+
+// Asks which columns hold the name, the skills and the metadata,
+// starting from the guessed mapping
+fn show_column_mapping_window<F>(ui: &UI, tb: &Rc<RefCell<TeamBuilder>>, on_confirm: F)
+where
+    F: FnMut(ColumnMapping) + 'static,
+{
+    let mut mapping_window = Window::new(ui, "Roster columns", 320, 200, WindowType::NoMenubar);
+    // None while the columns cannot be read with the chosen dialect
+    let mapping = Rc::new(RefCell::new(None));
+
+    let mut columns_vbox = VerticalBox::new(ui);
+    columns_vbox.set_padded(ui, true);
+
+    let mut columns_group = Group::new(ui, "Columns");
+    fill_columns_group(ui, &mut columns_group, tb, &mapping);
+
+    // How the file is written, the columns are read again on every change
+    let mut delimiter_cb = Combobox::new(ui);
+    for (name, _) in ROSTER_DELIMITERS.iter() {
+        delimiter_cb.append(ui, name);
+    }
+
+    let selected = ROSTER_DELIMITERS
+        .iter()
+        .position(|(_, delimiter)| *delimiter == tb.borrow().csv_dialect.delimiter)
+        .unwrap_or(0);
+    delimiter_cb.set_selected(ui, selected as i64);
+
+    delimiter_cb.on_selected(ui, {
+        let ui = ui.clone();
+        let tb = tb.clone();
+        let mapping = mapping.clone();
+        let mut columns_group = columns_group.clone();
+        move |index| {
+            tb.borrow_mut().csv_dialect.delimiter = ROSTER_DELIMITERS[index as usize].1;
+            fill_columns_group(&ui, &mut columns_group, &tb, &mapping);
+        }
+    });
+
+    let mut quote_entry = Entry::new(ui);
+    quote_entry.set_value(ui, &(tb.borrow().csv_dialect.quote as char).to_string());
+
+    quote_entry.on_changed(ui, {
+        let ui = ui.clone();
+        let tb = tb.clone();
+        let mapping = mapping.clone();
+        let mut columns_group = columns_group.clone();
+        move |value| {
+            // Waits for a single character
+            if let [quote] = value.as_bytes() {
+                tb.borrow_mut().csv_dialect.quote = *quote;
+                fill_columns_group(&ui, &mut columns_group, &tb, &mapping);
+            }
+        }
+    });
+
+    let mut headers_checkbox = Checkbox::new(ui, "The first row names the columns");
+    headers_checkbox.set_checked(ui, tb.borrow().csv_dialect.has_headers);
+
+    headers_checkbox.on_toggled(ui, {
+        let ui = ui.clone();
+        let tb = tb.clone();
+        let mapping = mapping.clone();
+        let mut columns_group = columns_group.clone();
+        move |checked| {
+            tb.borrow_mut().csv_dialect.has_headers = checked;
+            fill_columns_group(&ui, &mut columns_group, &tb, &mapping);
+        }
+    });
+
+    let mut dialect_hbox = HorizontalBox::new(ui);
+    dialect_hbox.set_padded(ui, true);
+    dialect_hbox.append(ui, Label::new(ui, "Delimiter"), LayoutStrategy::Compact);
+    dialect_hbox.append(ui, delimiter_cb, LayoutStrategy::Compact);
+    dialect_hbox.append(ui, Label::new(ui, "Quote"), LayoutStrategy::Compact);
+    dialect_hbox.append(ui, quote_entry, LayoutStrategy::Compact);
+
+    columns_vbox.append(ui, dialect_hbox, LayoutStrategy::Compact);
+    columns_vbox.append(ui, headers_checkbox, LayoutStrategy::Compact);
+    columns_vbox.append(ui, columns_group, LayoutStrategy::Stretchy);
+
+    let mut confirm_button = Button::new(ui, "Use these columns");
+    confirm_button.on_clicked(ui, {
+        let ui = ui.clone();
+        let mut mapping_window = mapping_window.clone();
+        let mut on_confirm = on_confirm;
+        move |_| {
+            let confirmed = mapping.borrow().clone();
+
+            if let Some(mapping) = confirmed {
+                mapping_window.hide(&ui);
+                on_confirm(mapping);
+            }
+        }
+    });
+    columns_vbox.append(ui, confirm_button, LayoutStrategy::Compact);
+
+    mapping_window.set_child(ui, columns_vbox);
+    mapping_window.show(ui);
+}
+
+// One combobox per column with the guessed role selected,
+// or the reason the columns cannot be read
+fn fill_columns_group(
+    ui: &UI,
+    group: &mut Group,
+    tb: &Rc<RefCell<TeamBuilder>>,
+    mapping: &Rc<RefCell<Option<ColumnMapping>>>,
+) {
+    let columns = tb.borrow().get_columns();
+    let guessed = tb.borrow().guess_column_mapping();
+
+    let (columns, guessed) = match (columns, guessed) {
+        (Ok(columns), Ok(guessed)) => (columns, guessed),
+        (Err(err), _) | (_, Err(err)) => {
+            *mapping.borrow_mut() = None;
+            group.set_child(
+                ui,
+                Label::new(ui, &format!("Cannot read the columns: {}", err)),
+            );
+            return;
+        }
+    };
+
+    let mut roles_vbox = VerticalBox::new(ui);
+    roles_vbox.set_padded(ui, true);
+
+    for (column, name) in columns.iter().enumerate() {
+        let mut column_hbox = HorizontalBox::new(ui);
+        column_hbox.set_padded(ui, true);
+
+        let mut role_cb = Combobox::new(ui);
+        for role in COLUMN_ROLES.iter() {
+            role_cb.append(ui, &role.to_string());
+        }
+
+        let selected = COLUMN_ROLES
+            .iter()
+            .position(|&role| role == guessed.roles[column])
+            .unwrap_or(0);
+        role_cb.set_selected(ui, selected as i64);
+
+        role_cb.on_selected(ui, {
+            let mapping = mapping.clone();
+            move |index| {
+                if let Some(mapping) = mapping.borrow_mut().as_mut() {
+                    mapping.roles[column] = COLUMN_ROLES[index as usize];
+                }
+            }
+        });
+
+        column_hbox.append(ui, Label::new(ui, name), LayoutStrategy::Stretchy);
+        column_hbox.append(ui, role_cb, LayoutStrategy::Compact);
+        roles_vbox.append(ui, column_hbox, LayoutStrategy::Compact);
+    }
+
+    *mapping.borrow_mut() = Some(guessed);
+    group.set_child(ui, roles_vbox);
+}
+
+// One checkbox per person, ticking it pins the person to this team
+// so the next generated teams keep them there
+fn fill_team_group(ui: &UI, group: &mut Group, tb: &Rc<RefCell<TeamBuilder>>, index: usize) {
+    let mut people_vbox = VerticalBox::new(ui);
+
+    let people = match tb.borrow().teams.get(index) {
+        Some(team) => team.people.clone(),
+        None => Vec::new(),
+    };
+
+    for person in people {
+        let mut checkbox = Checkbox::new(
+            ui,
+            &format!("{} [{:.1}]", person.surname, person.average_skill_level),
+        );
+        checkbox.set_checked(ui, tb.borrow().pins.get(&person.surname) == Some(&index));
+
+        checkbox.on_toggled(ui, {
+            let tb = tb.clone();
+            move |checked| {
+                if checked {
+                    tb.borrow_mut().pin_person(&person.surname, index);
+                } else {
+                    tb.borrow_mut().unpin_person(&person.surname);
+                }
+            }
+        });
+
+        people_vbox.append(ui, checkbox, LayoutStrategy::Compact);
+    }
+
+    group.set_child(ui, people_vbox);
+}
+
+// Makes the rounds with the strategy and skill chosen for the teams
+fn get_schedule(
+    tb: &Rc<RefCell<TeamBuilder>>,
+    state: &Rc<RefCell<State>>,
+    rounds: usize,
+) -> Result<Vec<Vec<Team>>, TeamBuilderError> {
+    let seed = tb.borrow().seed.unwrap_or_else(seed_from_time);
+    let strategies = get_strategies(seed);
+    let strategy = strategies[state.borrow().strategy].as_ref();
+    let sort_by = state.borrow().sort_by;
+
+    tb.borrow().generate_schedule(strategy, rounds, sort_by)
+}
+
+// Uses pbcopy, like the exported files are shown with open
+fn copy_to_clipboard(text: &str) -> std::io::Result<()> {
+    let mut child = Command::new("pbcopy").stdin(Stdio::piped()).spawn()?;
+
+    if let Some(stdin) = child.stdin.as_mut() {
+        stdin.write_all(text.as_bytes())?;
+    }
+
+    child.wait()?;
+
+    Ok(())
+}
+
+fn get_team_number_text(fixed_number_of_teams: bool, value: i32) -> String {
+    if fixed_number_of_teams {
+        format!("Teams: {}", value)
+    } else {
+        format!("Team members: {}", value)
+    }
+}
+
+// Tells what happened to the people that did not fit in the teams,
+// which only happens when asking for an exact number of people per team
+fn get_leftover_text(tb: &TeamBuilder, people_per_team: Option<usize>) -> String {
+    let remainder = match people_per_team.and_then(|size| tb.check_number_of_teams(size)) {
+        Some((_, remainder)) => remainder,
+        None => 0,
+    };
+
+    if remainder == 0 {
+        return String::new();
+    }
+
+    let mut text = format!("{} left over. {}", remainder, tb.remainder_policy);
+
+    if !tb.reserve.is_empty() {
+        let reserve = tb.reserve.iter().map(|person| &person.surname).join(", ");
+        text.push_str(&format!(": {}", reserve));
+    }
+
+    text
+}